@@ -44,9 +44,9 @@ impl Display for Name {
     }
 }
 
-impl Into<String> for &Name {
-    fn into(self) -> String {
-        format!("{}", self)
+impl From<&Name> for String {
+    fn from(name: &Name) -> Self {
+        format!("{}", name)
     }
 }
 
@@ -194,6 +194,17 @@ pub enum Edn {
     /// #{a b [1 2 3]}
     /// ```
     Set(BTreeSet<Edn>),
+
+    /// `#` followed immediately by a symbol starting with an alphabetic character indicates that
+    /// that symbol is a tag. A tag indicates the semantic interpretation of the following element.
+    /// The built-in tagged elements are `#inst` (an RFC-3339 formatted instant) and `#uuid` (a
+    /// canonical UUID string).
+    ///
+    /// ```
+    /// #inst "1985-04-12T23:20:50.52Z"
+    /// #uuid "f81d4fae-7dec-11d0-a765-00a0c91e6bf6"
+    /// ```
+    Tagged(Name, Box<Edn>),
 }
 
 impl Edn {
@@ -233,6 +244,8 @@ impl Display for Edn {
             Edn::Integer(value) => write!(f, "{}", value),
             Edn::Float(value) => write!(f, "{}", value),
             Edn::Symbol(value) => write!(f, "{}", value),
+            Edn::Keyword(value) => write!(f, ":{}", value),
+            Edn::Tagged(tag, value) => write!(f, "#{} {}", tag, value),
             Edn::Vector(values) => {
                 write!(f, "[")?;
                 for (index, value) in values.iter().enumerate() {
@@ -285,6 +298,8 @@ fn edn(input: &str) -> IResult<&str, Edn> {
         delimited(char('['), edns, char(']')).map(Edn::Vector),
         delimited(char('('), edns, char(')')).map(Edn::List),
         delimited(tag("#{"), edns, char('}')).map(|xs| Edn::Set(xs.into_iter().collect())),
+        preceded(char('#'), separated_pair(name, ws, edn))
+            .map(|(tag, value)| Edn::Tagged(tag, Box::new(value))),
         delimited(char('{'), entries, char('}')).map(|xs| Edn::Map(xs.into_iter().collect())),
         preceded(char(':'), name).map(Edn::Keyword),
         name.map(Edn::Symbol),
@@ -460,6 +475,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tagged() {
+        let result = Edn::try_from(r#"#inst "1985-04-12T23:20:50.52Z""#);
+
+        assert_eq!(
+            result,
+            Ok(Edn::Tagged(
+                Name::from("inst"),
+                Box::new(Edn::string("1985-04-12T23:20:50.52Z"))
+            ))
+        );
+    }
+
     mod format {
         use super::*;
 
@@ -498,6 +526,20 @@ mod tests {
             assert_eq!(format!("{}", namespaced), "foo/bar");
         }
 
+        #[test]
+        fn test_keyword() {
+            let edn = Edn::Keyword(Name::namespaced("foo", "bar"));
+
+            assert_eq!(format!("{}", edn), ":foo/bar");
+        }
+
+        #[test]
+        fn test_tagged() {
+            let edn = Edn::Tagged(Name::from("uuid"), Box::new(Edn::string("foo")));
+
+            assert_eq!(format!("{}", edn), r#"#uuid "foo""#);
+        }
+
         #[test]
         fn test_empty_vector() {
             assert_eq!(format!("{}", Edn::Vector(vec![])), "[]");
@@ -3,6 +3,10 @@ use rustomic::datom::Value;
 use rustomic::query::clause::*;
 use rustomic::query::pattern::*;
 use rustomic::query::{Find, Query};
+use rustomic::tx::{EntityOperation, OperatedEntity, Transaction};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use uuid::Uuid;
 
 enum State {
     Begin,
@@ -49,6 +53,88 @@ pub fn parse(input: &str) -> Result<Query, String> {
     Ok(query)
 }
 
+/// Parses transaction data, a vector of `[:db/add entity attribute value]` and
+/// `[:db/retract entity attribute value]` statements. Any other keyword in operation position
+/// invokes the transaction function registered under that name, e.g. `[:my/inc 42 1]`.
+pub fn parse_transaction(input: &str) -> Result<Transaction, String> {
+    let edn = Edn::try_from(input)?;
+    let Edn::Vector(statements) = edn else {
+        return Err("Expected a vector of statements".to_string());
+    };
+    statements.into_iter().enumerate().try_fold(
+        Transaction::new(),
+        |transaction, (index, statement)| {
+            // Positions are 1-based, as displayed to users
+            parse_statement(transaction, statement)
+                .map_err(|err| format!("statement {}: {}", index + 1, err))
+        },
+    )
+}
+
+fn parse_statement(transaction: Transaction, statement: Edn) -> Result<Transaction, String> {
+    let Edn::Vector(parts) = statement else {
+        return Err(format!("expected [:op ...], got {}", statement));
+    };
+    let mut parts = parts.into_iter();
+    let Some(Edn::Keyword(op)) = parts.next() else {
+        return Err("expected operation keyword, e.g. [:db/add e a v]".to_string());
+    };
+    let args = parts.collect::<Vec<_>>();
+    let transaction = match String::from(&op).as_str() {
+        "db/add" => transaction.with(parse_operation(args, "db/add", |e, a, v| e.assert(a, v))?),
+        "db/retract" => transaction.with(parse_operation(args, "db/retract", |e, a, v| {
+            e.retract(a, v)
+        })?),
+        name => {
+            let args = args
+                .into_iter()
+                .map(parse_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            transaction.call(name, args)
+        }
+    };
+    Ok(transaction)
+}
+
+fn parse_operation(
+    parts: Vec<Edn>,
+    name: &str,
+    op: fn(EntityOperation, AttributeIdentifier, Value) -> EntityOperation,
+) -> Result<EntityOperation, String> {
+    let [entity, attribute, value] =
+        <[Edn; 3]>::try_from(parts).map_err(|_| format!("expected [:{} e a v]", name))?;
+    let operation = match entity {
+        Edn::Integer(id) => EntityOperation::on_id(parse_id(id)?),
+        Edn::String(temp_id) => EntityOperation::on_temp_id(&temp_id),
+        Edn::Vector(lookup_ref) => match parse_lookup_ref(&lookup_ref)? {
+            Pattern::Constant(EntityIdentifier::LookupRef(lookup_ref)) => {
+                EntityOperation::new(OperatedEntity::LookupRef(lookup_ref))
+            }
+            _ => return Err("invalid lookup ref".to_string()),
+        },
+        edn => {
+            return Err(format!(
+                "invalid entity {}, expected ID, temp ID or lookup ref",
+                edn
+            ))
+        }
+    };
+    let attribute = match attribute {
+        Edn::Keyword(name) => AttributeIdentifier::Ident(String::from(&name)),
+        Edn::Integer(id) => AttributeIdentifier::Id(parse_id(id)?),
+        edn => return Err(format!("invalid attribute {}, expected ident or ID", edn)),
+    };
+    Ok(op(operation, attribute, parse_value(value)?))
+}
+
+fn parse_id(id: i64) -> Result<u64, String> {
+    u64::try_from(id).map_err(|_| format!("Invalid ID: {}", id))
+}
+
+fn parse_value(edn: Edn) -> Result<Value, String> {
+    Value::try_from(edn).map_err(|Unsupported(edn)| format!("Unsupported value: {}", edn))
+}
+
 #[derive(Debug)]
 pub struct Unsupported(Edn);
 
@@ -58,21 +144,45 @@ impl TryFrom<Edn> for Value {
     fn try_from(value: Edn) -> Result<Self, Self::Error> {
         match value {
             Edn::Nil => Ok(Self::Nil),
+            Edn::Boolean(value) => Ok(Self::Bool(value)),
             Edn::Integer(value) => Ok(Self::I64(value)),
+            Edn::Float(value) => Ok(Self::Double(value)),
             Edn::String(value) => Ok(Self::Str(value)),
+            Edn::Keyword(name) => Ok(Self::Keyword(String::from(&name))),
+            Edn::Vector(elements) => elements
+                .into_iter()
+                .map(Self::try_from)
+                .collect::<Result<_, _>>()
+                .map(Self::Tuple),
+            Edn::Tagged(tag, tagged) => match (tag.to_string().as_str(), tagged.as_ref()) {
+                ("inst", Edn::String(inst)) => parse_instant(inst)
+                    .map(Self::Instant)
+                    .ok_or(Unsupported(Edn::Tagged(tag, tagged))),
+                ("uuid", Edn::String(uuid)) => Uuid::parse_str(uuid)
+                    .map(Self::Uuid)
+                    .map_err(|_| Unsupported(Edn::Tagged(tag, tagged))),
+                _ => Err(Unsupported(Edn::Tagged(tag, tagged))),
+            },
             _ => Err(Unsupported(value)),
         }
     }
 }
 
+/// Parses an RFC-3339 timestamp into milliseconds since the Unix epoch.
+fn parse_instant(inst: &str) -> Option<i64> {
+    let time = OffsetDateTime::parse(inst, &Rfc3339).ok()?;
+    i64::try_from(time.unix_timestamp_nanos() / 1_000_000).ok()
+}
+
 fn parse_clause(patterns: Vec<Edn>) -> Result<Clause, String> {
-    let entity = match patterns.get(0) {
+    let entity = match patterns.first() {
         Some(Edn::Symbol(Name {
             namespace: None,
             name,
         })) if name == "_" => Pattern::Blank,
         Some(Edn::Symbol(name)) => Pattern::Variable(name.into()),
-        Some(Edn::Integer(id)) => Pattern::Constant(*id as u64),
+        Some(Edn::Integer(id)) => Pattern::entity(parse_id(*id)?),
+        Some(Edn::Vector(lookup_ref)) => parse_lookup_ref(lookup_ref)?,
         // TODO: handle failures
         _ => Pattern::Blank,
     };
@@ -83,7 +193,7 @@ fn parse_clause(patterns: Vec<Edn>) -> Result<Clause, String> {
         })) if name == "_" => Pattern::Blank,
         Some(Edn::Symbol(name)) => Pattern::Variable(name.into()),
         Some(Edn::Keyword(name)) => Pattern::Constant(AttributeIdentifier::Ident(name.into())),
-        Some(Edn::Integer(id)) => Pattern::Constant(AttributeIdentifier::Id(*id as u64)),
+        Some(Edn::Integer(id)) => Pattern::Constant(AttributeIdentifier::Id(parse_id(*id)?)),
         // TODO: handle failures
         _ => Pattern::Blank,
     };
@@ -94,7 +204,11 @@ fn parse_clause(patterns: Vec<Edn>) -> Result<Clause, String> {
         })) if name == "_" => Pattern::Blank,
         Some(Edn::Symbol(name)) => Pattern::Variable(name.into()),
         // TODO: remove clone
-        Some(edn) => Pattern::Constant(edn.clone().try_into().unwrap()),
+        Some(edn) => Pattern::Constant(
+            edn.clone()
+                .try_into()
+                .map_err(|Unsupported(edn)| format!("Unsupported value: {}", edn))?,
+        ),
         // TODO: handle failures
         _ => Pattern::Blank,
     };
@@ -106,10 +220,23 @@ fn parse_clause(patterns: Vec<Edn>) -> Result<Clause, String> {
     })
 }
 
+fn parse_lookup_ref(parts: &[Edn]) -> Result<Pattern<EntityIdentifier>, String> {
+    match parts {
+        [Edn::Keyword(attribute), value] => {
+            let value = Value::try_from(value.clone())
+                .map_err(|Unsupported(edn)| format!("Unsupported value: {}", edn))?;
+            Ok(Pattern::lookup_ref(&String::from(attribute), value))
+        }
+        _ => Err("Invalid lookup ref".to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rustomic::datom::Op;
     use rustomic::query::Find;
+    use rustomic::tx::function::FunctionCall;
 
     #[test]
     fn test_empty_query() {
@@ -167,4 +294,112 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn parse_lookup_ref_entity() {
+        let query = parse(
+            r#"[:find ?born
+                        :where [[:artist/name "John Lenon"] :artist/born ?born]]"#,
+        );
+
+        assert!(query.is_ok());
+        let Query { clauses, .. } = query.unwrap();
+        assert_eq!(
+            clauses,
+            vec![Clause::new()
+                .with_entity(Pattern::lookup_ref("artist/name", "John Lenon"))
+                .with_attribute(Pattern::ident("artist/born"))
+                .with_value(Pattern::variable("?born"))]
+        );
+    }
+
+    #[test]
+    fn parse_transaction_statements() {
+        let transaction = parse_transaction(
+            r#"[[:db/add "john" :artist/name "John Lenon"]
+                [:db/retract [:artist/name "Paul McCartney"] :artist/born 1942]]"#,
+        );
+
+        assert!(transaction.is_ok());
+        let operations = transaction.unwrap().operations;
+        assert_eq!(2, operations.len());
+        assert!(
+            matches!(&operations[0].entity, OperatedEntity::TempId(temp_id) if temp_id == "john")
+        );
+        assert!(matches!(
+            &operations[1].entity,
+            OperatedEntity::LookupRef(lookup_ref) if lookup_ref.attribute == "artist/name"
+        ));
+        assert_eq!(Op::Retract, operations[1].attributes[0].op);
+    }
+
+    #[test]
+    fn parse_transaction_function_call() {
+        let transaction = parse_transaction(r#"[[:person/inc 42 "person/born" 1]]"#);
+
+        assert!(transaction.is_ok());
+        assert_eq!(
+            transaction.unwrap().calls,
+            vec![FunctionCall {
+                name: "person/inc".to_string(),
+                args: vec![Value::I64(42), Value::str("person/born"), Value::I64(1)],
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_typed_values() {
+        let parse_value = |input: &str| Value::try_from(Edn::try_from(input).unwrap()).ok();
+
+        assert_eq!(Some(Value::Bool(true)), parse_value("true"));
+        assert_eq!(Some(Value::from(4.2)), parse_value("4.2"));
+        assert_eq!(Some(Value::keyword("color/red")), parse_value(":color/red"));
+        assert_eq!(
+            Some(Value::Instant(482196050520)),
+            parse_value(r#"#inst "1985-04-12T23:20:50.52Z""#)
+        );
+        assert_eq!(
+            Some(Value::Uuid(Uuid::from_u128(
+                0xf81d4fae_7dec_11d0_a765_00a0c91e6bf6
+            ))),
+            parse_value(r#"#uuid "f81d4fae-7dec-11d0-a765-00a0c91e6bf6""#)
+        );
+        assert_eq!(
+            Some(Value::Tuple(vec![
+                Value::I64(1),
+                Value::Nil,
+                Value::str("foo")
+            ])),
+            parse_value(r#"[1 nil "foo"]"#)
+        );
+        assert_eq!(None, parse_value(r#"#inst "yesterday""#));
+        assert_eq!(None, parse_value(r#"#foo "bar""#));
+    }
+
+    #[test]
+    fn reject_invalid_transaction_statement() {
+        let transaction = parse_transaction(r#"[[:db/add "john" :artist/name]]"#);
+
+        assert!(transaction.is_err());
+    }
+
+    #[test]
+    fn report_position_and_shape_of_invalid_statement() {
+        let transaction = parse_transaction(
+            r#"[[:db/add "john" :artist/name "John"]
+                [:db/retract "john" :artist/name]]"#,
+        );
+
+        assert_eq!(
+            Err("statement 2: expected [:db/retract e a v]".to_string()),
+            transaction.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn reject_negative_ids() {
+        assert!(parse_transaction(r#"[[:db/add -1 :artist/name "John"]]"#).is_err());
+        assert!(parse_transaction(r#"[[:db/add "john" -1 "John"]]"#).is_err());
+        assert!(parse("[:find ?name :where [-1 :artist/name ?name]]").is_err());
+    }
 }
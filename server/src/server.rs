@@ -1,26 +1,33 @@
-use rustomic::clock::Instant;
+use rustomic::clock::SystemClock;
+use rustomic::connection::Connection;
+use rustomic::datom::Datom;
+use rustomic::datom::Op;
 use rustomic::datom::Value;
-use rustomic::query::database::Database;
+use rustomic::query::Query;
 use rustomic::query::QueryError;
 use rustomic::schema::attribute::*;
 use rustomic::schema::default::default_datoms;
-use rustomic::storage::attribute_resolver::AttributeResolver;
 use rustomic::storage::disk::DiskStorage;
 use rustomic::storage::disk::DiskStorageError;
-use rustomic::storage::disk::ReadOnly;
+use rustomic::storage::disk::ReadWrite;
 use rustomic::storage::ReadStorage;
 use rustomic::storage::WriteStorage;
-use rustomic::tx::transactor;
+use rustomic::tx::report::TransactionReport;
 use rustomic::tx::EntityOperation;
 use rustomic::tx::Transaction;
 use server::query_service_server::QueryServiceServer;
 use std::collections::HashSet;
-use std::time::SystemTime;
+use std::pin::Pin;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::Stream;
+use tokio_stream::StreamExt;
 use tonic::{transport::Server, Request, Response, Status};
 
 use server::query_service_server::QueryService;
 use server::QueryRequest;
 use server::QueryResponse;
+use server::SubscribeRequest;
+use server::TransactRequest;
 
 mod edn;
 mod parser;
@@ -32,37 +39,35 @@ pub mod server {
 }
 
 pub struct QueryServiceImpl {
-    storage: DiskStorage<ReadOnly>,
-    resolver: AttributeResolver,
+    conn: Connection<DiskStorage<ReadWrite>>,
 }
 
 impl QueryServiceImpl {
     async fn query_impl(
         &self,
-        request: QueryRequest,
+        query: Query,
     ) -> Result<HashSet<Vec<Value>>, QueryError<DiskStorageError>> {
-        let basis_tx = self.storage.latest_entity_id()?;
-        let query = parser::parse(&request.query).map_err(|_| QueryError::Error)?;
-        println!("@@@ request: {:?}", &request);
-        println!("@@@ parsed query: {:?}", &query);
-        let results = Database::new(basis_tx)
-            .query(&self.storage, &self.resolver, query)
-            .await?;
-        results.collect()
+        let db = self.conn.db().await?;
+        let results = self.conn.query(&db, query).await?;
+        results.into_iter().collect()
     }
 }
 
 #[tonic::async_trait]
 impl QueryService for QueryServiceImpl {
+    type SubscribeStream =
+        Pin<Box<dyn Stream<Item = Result<server::TransactionReport, Status>> + Send>>;
+
     async fn query(
         &self,
         request: Request<QueryRequest>,
     ) -> Result<Response<QueryResponse>, Status> {
+        let request = request.into_inner();
+        let query = parser::parse(&request.query).map_err(Status::invalid_argument)?;
         let results = self
-            .query_impl(request.into_inner())
+            .query_impl(query)
             .await
             .map_err(|err| Status::unknown(err.to_string()))?;
-        println!("@@@ results: {:?}", &results);
         Ok(Response::new(QueryResponse {
             assignments: results
                 .iter()
@@ -70,15 +75,90 @@ impl QueryService for QueryServiceImpl {
                 .collect(),
         }))
     }
+
+    #[allow(clippy::result_large_err)]
+    async fn subscribe(
+        &self,
+        _request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let reports = self.conn.subscribe().map(|report| match report {
+            Ok(report) => Ok(server::TransactionReport::from(report.as_ref())),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => Err(Status::data_loss(format!(
+                "subscriber lagged behind, {} reports were skipped",
+                skipped
+            ))),
+        });
+        Ok(Response::new(Box::pin(reports)))
+    }
+
+    async fn transact(
+        &self,
+        request: Request<TransactRequest>,
+    ) -> Result<Response<server::TransactionReport>, Status> {
+        let request = request.into_inner();
+        let transaction =
+            parser::parse_transaction(&request.tx_data).map_err(Status::invalid_argument)?;
+        let report = self
+            .conn
+            .transact(transaction)
+            .await
+            .map_err(|err| Status::unknown(err.to_string()))?;
+        Ok(Response::new(server::TransactionReport::from(&report)))
+    }
+}
+
+impl From<&TransactionReport> for server::TransactionReport {
+    fn from(report: &TransactionReport) -> Self {
+        Self {
+            db_before: report.db_before.basis_tx(),
+            db_after: report.db_after.basis_tx(),
+            tx_data: report.tx_data.iter().map(server::Datom::from).collect(),
+            temp_ids: report.temp_ids.clone(),
+        }
+    }
+}
+
+impl From<&Datom> for server::Datom {
+    fn from(datom: &Datom) -> Self {
+        Self {
+            entity: datom.entity,
+            attribute: datom.attribute,
+            value: Some(server::Value::from(&datom.value)),
+            tx: datom.tx,
+            added: datom.op == Op::Assert,
+        }
+    }
+}
+
+impl From<&Value> for server::Value {
+    fn from(value: &Value) -> Self {
+        use server::value::Value as Proto;
+        let value = match value {
+            Value::Nil => Proto::Nil(()),
+            Value::I64(value) => Proto::I64(*value),
+            Value::U64(value) => Proto::U64(*value),
+            Value::Decimal(value) => Proto::Decimal(value.to_string()),
+            Value::Str(value) => Proto::Str(value.clone()),
+            Value::Ref(value) => Proto::Ref(*value),
+            Value::Bool(value) => Proto::Bool(*value),
+            Value::Keyword(value) => Proto::Keyword(value.clone()),
+            Value::Instant(value) => Proto::Instant(*value),
+            Value::Uuid(value) => Proto::Uuid(value.to_string()),
+            Value::Double(value) => Proto::Double(value.0),
+            Value::Bytes(value) => Proto::Bytes(value.clone()),
+            Value::BigInt(value) => Proto::BigInt(value.to_signed_bytes_be().to_vec()),
+            Value::Tuple(elements) => Proto::Tuple(server::Tuple {
+                elements: elements.iter().map(server::Value::from).collect(),
+            }),
+        };
+        Self { value: Some(value) }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let resolver = AttributeResolver::new();
-    init_db(&resolver).await?;
-
-    let storage = DiskStorage::read_only(DB_PATH)?;
-    let query_service = QueryServiceImpl { storage, resolver };
+    let conn = init_db().await?;
+    let query_service = QueryServiceImpl { conn };
 
     let addr = "[::1]:50051".parse()?;
     println!("Starting server on {:?}...", &addr);
@@ -91,17 +171,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn init_db(resolver: &AttributeResolver) -> Result<(), Box<dyn std::error::Error>> {
+async fn init_db() -> Result<Connection<DiskStorage<ReadWrite>>, Box<dyn std::error::Error>> {
     let mut storage = DiskStorage::read_write(DB_PATH)?;
     if storage.latest_entity_id()? > 0 {
         // Looks like the DB already has some datoms saved, no need to re-create the schema.
-        return Ok(());
+        return Ok(Connection::new(storage, SystemClock));
     }
 
     storage.save(&default_datoms())?;
+    let conn = Connection::new(storage, SystemClock);
 
-    {
-        let tx = Transaction::new()
+    conn.transact(
+        Transaction::new()
             .with(AttributeDefinition::new("movie/name", ValueType::Str))
             .with(AttributeDefinition::new("movie/year", ValueType::U64))
             .with(AttributeDefinition::new("movie/director", ValueType::Ref).many())
@@ -109,14 +190,12 @@ async fn init_db(resolver: &AttributeResolver) -> Result<(), Box<dyn std::error:
             .with(AttributeDefinition::new("actor/name", ValueType::Str))
             .with(AttributeDefinition::new("artist/name", ValueType::Str))
             .with(AttributeDefinition::new("release/name", ValueType::Str))
-            .with(AttributeDefinition::new("release/artists", ValueType::Ref).many());
-
-        let tx_result = transactor::transact(&storage, resolver, now(), tx).await?;
-        storage.save(&tx_result.tx_data)?;
-    }
+            .with(AttributeDefinition::new("release/artists", ValueType::Ref).many()),
+    )
+    .await?;
 
-    {
-        let tx = Transaction::new()
+    conn.transact(
+        Transaction::new()
             .with(EntityOperation::on_temp_id("john").assert("artist/name", "John Lenon"))
             .with(EntityOperation::on_temp_id("paul").assert("artist/name", "Paul McCartney"))
             .with(
@@ -124,20 +203,9 @@ async fn init_db(resolver: &AttributeResolver) -> Result<(), Box<dyn std::error:
                     .assert("release/name", "Abbey Road")
                     .set_reference("release/artists", "john")
                     .set_reference("release/artists", "paul"),
-            );
-
-        let tx_result = transactor::transact(&storage, resolver, now(), tx).await?;
-        storage.save(&tx_result.tx_data)?;
-    }
-
-    Ok(())
-}
-
-fn now() -> Instant {
-    Instant(
-        SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs(),
+            ),
     )
+    .await?;
+
+    Ok(conn)
 }
@@ -1,9 +1,24 @@
+use std::time::SystemTime;
+
 pub struct Instant(pub u64);
 
 pub trait Clock {
     fn now(&self) -> u64;
 }
 
+/// A clock reading the system time, in seconds since the Unix epoch.
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs()
+    }
+}
+
 #[derive(Default)]
 pub struct MockClock {
     now: u64,
@@ -0,0 +1,395 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::RwLock;
+use tokio::sync::RwLockReadGuard;
+
+use crate::clock::Clock;
+use crate::clock::Instant;
+use crate::query::database::Database;
+use crate::query::Query;
+use crate::query::QueryResult;
+use crate::storage::attribute_resolver::AttributeResolver;
+use crate::storage::attribute_resolver::ResolveError;
+use crate::storage::ReadStorage;
+use crate::storage::SnapshotStorage;
+use crate::storage::WriteStorage;
+use crate::tx::function::TransactionFunctions;
+use crate::tx::report::TransactionReport;
+use crate::tx::report::TxReportQueue;
+use crate::tx::report::TxReportStream;
+use crate::tx::transactor;
+use crate::tx::transactor::TransactorConfig;
+use crate::tx::Transaction;
+use crate::tx::TransactionError;
+
+/// A connection to a database, owning its storage, attribute resolver and clock.
+///
+/// Transactions are sent to a single transactor task, which processes them one at a time: each
+/// one is resolved against the latest state of the storage, saved and published to subscribers
+/// before the next one begins. Queries may run concurrently from any number of connection
+/// handles, and only wait while a transaction is being saved.
+pub struct Connection<S: for<'a> ReadStorage<'a> + WriteStorage> {
+    shared: Arc<Shared<S>>,
+    transactions: mpsc::Sender<TransactRequest<S>>,
+    tx_reports: TxReportQueue,
+}
+
+struct Shared<S> {
+    storage: RwLock<S>,
+    resolver: AttributeResolver,
+    functions: TransactionFunctions<S>,
+    config: TransactorConfig,
+}
+
+struct TransactRequest<S: for<'a> ReadStorage<'a> + WriteStorage> {
+    transaction: Transaction,
+    reply: oneshot::Sender<TransactResult<S>>,
+}
+
+type ReadError<S> = <S as ReadStorage<'static>>::Error;
+type WriteError<S> = <S as WriteStorage>::Error;
+type TransactResult<S> = Result<TransactionReport, ConnectionError<ReadError<S>, WriteError<S>>>;
+
+const TRANSACTIONS_CAPACITY: usize = 64;
+
+impl<S: for<'a> ReadStorage<'a> + WriteStorage> Clone for Connection<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            transactions: self.transactions.clone(),
+            tx_reports: self.tx_reports.clone(),
+        }
+    }
+}
+
+impl<S, E> Connection<S>
+where
+    S: for<'a> ReadStorage<'a, Error = E> + SnapshotStorage + WriteStorage + Send + Sync + 'static,
+    E: Send + 'static,
+    WriteError<S>: Send,
+{
+    /// Creates a connection and spawns its transactor task on the current tokio runtime.
+    /// The task stops once all handles to the connection are dropped.
+    ///
+    /// # Panics
+    /// If called outside of a tokio runtime
+    pub fn new(storage: S, clock: impl Clock + Send + 'static) -> Self {
+        Self::with_functions(storage, clock, TransactionFunctions::new())
+    }
+
+    /// Creates a connection whose transactions may invoke the given transaction `functions`.
+    ///
+    /// # Panics
+    /// If called outside of a tokio runtime
+    pub fn with_functions(
+        storage: S,
+        clock: impl Clock + Send + 'static,
+        functions: TransactionFunctions<S>,
+    ) -> Self {
+        Self::with_config(storage, clock, functions, TransactorConfig::default())
+    }
+
+    /// Creates a connection whose transactor enforces the limits in `config`.
+    ///
+    /// # Panics
+    /// If called outside of a tokio runtime
+    pub fn with_config(
+        storage: S,
+        clock: impl Clock + Send + 'static,
+        functions: TransactionFunctions<S>,
+        config: TransactorConfig,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            storage: RwLock::new(storage),
+            resolver: AttributeResolver::new(),
+            functions,
+            config,
+        });
+        let tx_reports = TxReportQueue::new();
+        let (transactions, receiver) = mpsc::channel(TRANSACTIONS_CAPACITY);
+        tokio::spawn(run_transactor(
+            shared.clone(),
+            clock,
+            tx_reports.clone(),
+            receiver,
+        ));
+        Self {
+            shared,
+            transactions,
+            tx_reports,
+        }
+    }
+
+    pub fn resolver(&self) -> &AttributeResolver {
+        &self.shared.resolver
+    }
+
+    /// Returns a read handle to the underlying storage. Transactions can't be saved while the
+    /// handle is held.
+    pub async fn storage(&self) -> RwLockReadGuard<'_, S> {
+        self.shared.storage.read().await
+    }
+
+    pub fn subscribe(&self) -> TxReportStream {
+        self.tx_reports.subscribe()
+    }
+
+    /// Returns the database value at the latest saved transaction.
+    ///
+    /// # Errors
+    /// Storage related errors
+    pub async fn db(&self) -> Result<Database, E> {
+        let storage = self.shared.storage.read().await;
+        Ok(Database::latest(storage.latest_entity_id()?))
+    }
+
+    /// Runs `query` against `db` and collects the results. The query reads from a single storage
+    /// snapshot, which is released once all results are collected. The storage is only locked
+    /// while taking the snapshot, so transactions can be saved while the query runs.
+    ///
+    /// # Errors
+    /// Query errors, see `QueryError`
+    pub async fn query(
+        &self,
+        db: &Database,
+        query: Query,
+    ) -> crate::query::Result<Vec<QueryResult<E>>, E> {
+        let snapshot = self.shared.storage.read().await.snapshot();
+        let results = db.query(&snapshot, &self.shared.resolver, query).await?;
+        Ok(results.collect())
+    }
+
+    /// Sends `transaction` to the transactor and waits for it to be saved.
+    ///
+    /// # Errors
+    /// Transaction errors, storage errors when saving the datoms failed, or
+    /// `ConnectionError::TransactorClosed` if the transactor task is no longer running
+    pub async fn transact(
+        &self,
+        transaction: Transaction,
+    ) -> Result<TransactionReport, ConnectionError<E, WriteError<S>>> {
+        let (reply, result) = oneshot::channel();
+        let request = TransactRequest { transaction, reply };
+        self.transactions
+            .send(request)
+            .await
+            .map_err(|_| ConnectionError::TransactorClosed)?;
+        result
+            .await
+            .map_err(|_| ConnectionError::TransactorClosed)?
+    }
+
+    /// Removes the history of the attributes identified by `idents` before `before_tx`, see
+    /// `WriteStorage::purge_history`. Transactions are not saved while history is purged.
+    ///
+    /// # Errors
+    /// `ConnectionError::ResolveError` if an attribute is not found, or storage errors
+    pub async fn purge_history(
+        &self,
+        idents: &[&str],
+        before_tx: u64,
+    ) -> Result<usize, ConnectionError<E, WriteError<S>>> {
+        let mut storage = self.shared.storage.write().await;
+        let mut attributes = Vec::with_capacity(idents.len());
+        for ident in idents {
+            let attribute = self
+                .shared
+                .resolver
+                .resolve(&*storage, ident, u64::MAX)
+                .await?;
+            attributes.push(attribute.id);
+        }
+        storage
+            .purge_history(&attributes, before_tx)
+            .map_err(ConnectionError::WriteError)
+    }
+}
+
+async fn run_transactor<S, E>(
+    shared: Arc<Shared<S>>,
+    clock: impl Clock,
+    tx_reports: TxReportQueue,
+    mut requests: mpsc::Receiver<TransactRequest<S>>,
+) where
+    S: for<'a> ReadStorage<'a, Error = E> + WriteStorage,
+{
+    while let Some(TransactRequest { transaction, reply }) = requests.recv().await {
+        let now = Instant(clock.now());
+        let result = transact(&shared, now, transaction).await;
+        if let Ok(report) = &result {
+            tx_reports.publish(report.clone());
+        }
+        // The caller might have stopped waiting for the result, which is fine.
+        let _ = reply.send(result);
+    }
+}
+
+async fn transact<S, E>(
+    shared: &Shared<S>,
+    now: Instant,
+    transaction: Transaction,
+) -> Result<TransactionReport, ConnectionError<E, WriteError<S>>>
+where
+    S: for<'a> ReadStorage<'a, Error = E> + WriteStorage,
+{
+    // Only the transactor writes to the storage, so nothing can be saved between resolving the
+    // transaction and saving its datoms, even though readers are not blocked while resolving.
+    let (db_before, result) = {
+        let storage = shared.storage.read().await;
+        let db_before = Database::latest(
+            storage
+                .latest_entity_id()
+                .map_err(TransactionError::StorageError)?,
+        );
+        let result = transactor::transact(
+            &*storage,
+            &shared.resolver,
+            &shared.functions,
+            &shared.config,
+            now,
+            transaction,
+        )
+        .await?;
+        (db_before, result)
+    };
+
+    let mut storage = shared.storage.write().await;
+    storage
+        .save(&result.tx_data)
+        .map_err(ConnectionError::WriteError)?;
+    // Evict altered attributes before readers can see the new datoms.
+    shared.resolver.invalidate(&result.tx_data).await;
+    drop(storage);
+
+    Ok(TransactionReport::new(db_before, result))
+}
+
+#[derive(Debug, Error)]
+pub enum ConnectionError<R, W> {
+    #[error("transaction error")]
+    TransactionError(#[from] TransactionError<R>),
+    #[error("resolve error")]
+    ResolveError(#[from] ResolveError<R>),
+    #[error("write error")]
+    WriteError(W),
+    #[error("transactor is closed")]
+    TransactorClosed,
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use crate::clock::MockClock;
+    use crate::datom::Datom;
+    use crate::datom::Value;
+    use crate::query::clause::Clause;
+    use crate::query::pattern::Pattern;
+    use crate::query::Find;
+    use crate::schema::attribute::*;
+    use crate::schema::default::default_datoms;
+    use crate::schema::DB_TX_TIME_ID;
+    use crate::storage::memory::InMemoryStorage;
+    use crate::tx::EntityOperation;
+
+    use super::*;
+
+    fn connect() -> Connection<InMemoryStorage> {
+        let mut storage = InMemoryStorage::new();
+        storage
+            .save(&default_datoms())
+            .expect("Unable to save default datoms");
+        Connection::new(storage, MockClock::new())
+    }
+
+    #[tokio::test]
+    async fn save_transacted_datoms() {
+        let conn = connect();
+        let schema =
+            Transaction::new().with(AttributeDefinition::new("person/name", ValueType::Str));
+        conn.transact(schema).await.expect("Unable to transact");
+
+        let tx = Transaction::new().with(EntityOperation::on_new().assert("person/name", "Alice"));
+        let report = conn.transact(tx).await.expect("Unable to transact");
+
+        let tx_id = report.db_after.basis_tx();
+        assert!(report
+            .tx_data
+            .contains(&Datom::add(tx_id, DB_TX_TIME_ID, 0u64, tx_id)));
+
+        // [:find ?name :where [?person :person/name ?name]]
+        let db = conn.db().await.expect("Unable to get db");
+        let query = Query::new().find(Find::variable("?name")).r#where(
+            Clause::new()
+                .with_entity(Pattern::variable("?person"))
+                .with_attribute(Pattern::ident("person/name"))
+                .with_value(Pattern::variable("?name")),
+        );
+        let results: Vec<_> = conn
+            .query(&db, query)
+            .await
+            .expect("Unable to query")
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(vec![vec![Value::str("Alice")]], results);
+    }
+
+    #[tokio::test]
+    async fn publish_reports_to_subscribers() {
+        let conn = connect();
+        let mut subscriber = conn.subscribe();
+
+        let schema =
+            Transaction::new().with(AttributeDefinition::new("person/name", ValueType::Str));
+        let report = conn.transact(schema).await.expect("Unable to transact");
+
+        let received = subscriber.next().await.unwrap().unwrap();
+        assert_eq!(report.db_after.basis_tx(), received.db_after.basis_tx());
+        assert_eq!(report.tx_data, received.tx_data);
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_is_not_saved() {
+        let conn = connect();
+        let db_before = conn.db().await.expect("Unable to get db");
+
+        let tx = Transaction::new().with(EntityOperation::on_new().assert("person/name", "Alice"));
+        let result = conn.transact(tx).await;
+
+        assert!(result.is_err());
+        let db_after = conn.db().await.expect("Unable to get db");
+        assert_eq!(db_before.basis_tx(), db_after.basis_tx());
+    }
+
+    #[tokio::test]
+    async fn serialize_concurrent_transactions() {
+        let conn = connect();
+        let schema = Transaction::new()
+            .with(AttributeDefinition::new("person/email", ValueType::Str).unique());
+        conn.transact(schema).await.expect("Unable to transact");
+
+        // Both transactions are valid on their own, but only one of them can be saved.
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let conn = conn.clone();
+                tokio::spawn(async move {
+                    let tx = Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/email", "foo@bar.com"));
+                    conn.transact(tx).await.is_ok()
+                })
+            })
+            .collect();
+
+        let mut succeeded = 0;
+        for handle in handles {
+            if handle.await.unwrap() {
+                succeeded += 1;
+            }
+        }
+        assert_eq!(1, succeeded);
+    }
+}
@@ -1,4 +1,8 @@
+use std::cmp::Ordering;
+
+use ordered_float::OrderedFloat;
 use rust_decimal::prelude::*;
+use uuid::Uuid;
 
 /// A datom is an immutable atomic fact that represents the addition or retraction of a relation
 /// between an entity, an attribute, a value, and a transaction.
@@ -41,12 +45,24 @@ pub enum Value {
     Decimal(Decimal),
     Str(String),
     Ref(u64),
+    Bool(bool),
+    Keyword(String),
+    Instant(i64), // Milliseconds since the Unix epoch.
+    Uuid(Uuid),
+    Double(OrderedFloat<f64>),
+    Bytes(Vec<u8>),
+    BigInt(BigInt),
+    Tuple(Vec<Value>),
 }
 
 impl Value {
     pub fn str(str: &str) -> Self {
         Self::Str(str.to_string())
     }
+
+    pub fn keyword(keyword: &str) -> Self {
+        Self::Keyword(keyword.to_string())
+    }
 }
 
 impl From<i32> for Value {
@@ -91,6 +107,120 @@ impl From<String> for Value {
     }
 }
 
+impl From<Vec<u8>> for Value {
+    fn from(val: Vec<u8>) -> Self {
+        Self::Bytes(val)
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(val: BigInt) -> Self {
+        Self::BigInt(val)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(val: bool) -> Self {
+        Self::Bool(val)
+    }
+}
+
+impl From<Uuid> for Value {
+    fn from(val: Uuid) -> Self {
+        Self::Uuid(val)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(val: f64) -> Self {
+        Self::Double(OrderedFloat(val))
+    }
+}
+
+/// An arbitrary-precision integer, kept as its shortest big-endian two's complement bytes.
+#[derive(Hash, Eq, PartialEq, Debug, Clone)]
+pub struct BigInt(Vec<u8>);
+
+impl BigInt {
+    /// ```
+    /// use rustomic::datom::BigInt;
+    ///
+    /// assert_eq!(BigInt::from(-1), BigInt::from_signed_bytes_be(&[0xff, 0xff, 0xff]));
+    /// assert_eq!(BigInt::from(128), BigInt::from_signed_bytes_be(&[0x00, 0x00, 0x80]));
+    /// assert_eq!(BigInt::from(0), BigInt::from_signed_bytes_be(&[]));
+    /// ```
+    pub fn from_signed_bytes_be(bytes: &[u8]) -> Self {
+        let mut start = 0;
+        // Drop leading bytes which only extend the sign of the following byte.
+        while let Some(&byte) = bytes.get(start) {
+            let next_negative = bytes.get(start + 1).map(|next| next & 0x80 != 0);
+            match (byte, next_negative) {
+                (0x00, None | Some(false)) | (0xff, Some(true)) => start += 1,
+                _ => break,
+            }
+        }
+        Self(bytes[start..].to_vec())
+    }
+
+    pub fn to_signed_bytes_be(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0.first().is_some_and(|byte| byte & 0x80 != 0)
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(val: i128) -> Self {
+        Self::from_signed_bytes_be(&val.to_be_bytes())
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(val: i64) -> Self {
+        i128::from(val).into()
+    }
+}
+
+impl From<i32> for BigInt {
+    fn from(val: i32) -> Self {
+        i128::from(val).into()
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(val: u64) -> Self {
+        i128::from(val).into()
+    }
+}
+
+impl Ord for BigInt {
+    /// ```
+    /// use rustomic::datom::BigInt;
+    ///
+    /// assert!(BigInt::from(-129) < BigInt::from(-128));
+    /// assert!(BigInt::from(-1) < BigInt::from(0));
+    /// assert!(BigInt::from(127) < BigInt::from(128));
+    /// assert!(BigInt::from(i128::MAX) > BigInt::from(u64::MAX));
+    /// ```
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Both numbers have no redundant sign bytes, so the longer one has a larger magnitude.
+        match (self.is_negative(), other.is_negative()) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.0.len().cmp(&other.0.len()).then(self.0.cmp(&other.0)),
+            (true, true) => other.0.len().cmp(&self.0.len()).then(self.0.cmp(&other.0)),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
 pub enum Op {
     Assert,
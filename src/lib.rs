@@ -1,4 +1,5 @@
 pub mod clock;
+pub mod connection;
 pub mod datom;
 pub mod query;
 pub mod schema;
@@ -9,15 +10,19 @@ pub mod tx;
 mod tests {
     use googletest::prelude::*;
     use std::result::Result;
-    use std::time::SystemTime;
 
-    use crate::clock::Instant;
+    use crate::clock::SystemClock;
+    use crate::connection::Connection;
+    use crate::connection::ConnectionError;
     use crate::schema::default::default_datoms;
+    use crate::schema::DB_ATTR_IDENT_IDENT;
     use crate::schema::DB_TX_TIME_ID;
-    use crate::storage::attribute_resolver::AttributeResolver;
     use crate::storage::memory::InMemoryStorage;
     use crate::storage::ReadStorage;
     use crate::storage::WriteStorage;
+    use crate::tx::function::TransactionFunctions;
+    use crate::tx::report::TransactionReport;
+    use crate::tx::transactor::TransactorConfig;
 
     use super::datom::*;
     use super::query::clause::*;
@@ -26,83 +31,80 @@ mod tests {
     use super::query::*;
     use super::schema::attribute::*;
 
-    use super::tx::transactor;
     use super::tx::*;
 
     struct Sut {
-        resolver: AttributeResolver,
-        storage: InMemoryStorage,
-        last_tx: u64,
+        conn: Connection<InMemoryStorage>,
     }
 
     type StorageError<'a> = <InMemoryStorage as ReadStorage<'a>>::Error;
 
     impl Sut {
         async fn new() -> Self {
-            let resolver = AttributeResolver::new();
+            Self::with_functions(TransactionFunctions::new()).await
+        }
+
+        async fn with_functions(functions: TransactionFunctions<InMemoryStorage>) -> Self {
+            Self::with_config(functions, TransactorConfig::default()).await
+        }
+
+        async fn with_config(
+            functions: TransactionFunctions<InMemoryStorage>,
+            config: TransactorConfig,
+        ) -> Self {
             let mut storage = InMemoryStorage::new();
             storage
                 .save(&default_datoms())
                 .expect("Unable to save default datoms");
 
             let mut sut = Self {
-                resolver,
-                storage,
-                last_tx: 0,
+                conn: Connection::with_config(storage, SystemClock, functions, config),
             };
 
             sut.transact(create_schema()).await;
             sut
         }
 
-        async fn transact(&mut self, transaction: Transaction) -> TransctionResult {
-            let result = self
-                .try_transact(transaction)
-                .await
-                .expect("Unable to transact");
-            self.storage.save(&result.tx_data).expect("Unable to save");
-            self.last_tx = result.tx_id;
-            result
+        /// Creates a SUT with `schema` transacted on top of the default schema.
+        async fn with_schema(config: TransactorConfig, schema: Transaction) -> Self {
+            let mut sut = Self::with_config(TransactionFunctions::new(), config).await;
+            sut.transact(schema).await;
+            sut
         }
 
-        async fn try_transact(&self, transaction: Transaction) -> Option<TransctionResult> {
-            transactor::transact(&self.storage, &self.resolver, now(), transaction)
+        async fn transact(&mut self, transaction: Transaction) -> TransactionReport {
+            self.try_transact(transaction)
                 .await
-                .ok()
+                .expect("Unable to transact")
+        }
+
+        async fn try_transact(&self, transaction: Transaction) -> Option<TransactionReport> {
+            self.conn.transact(transaction).await.ok()
         }
 
         async fn query(&mut self, query: Query) -> Vec<Vec<Value>> {
-            self.query_at_snapshot(self.last_tx, query).await
+            let db = self.conn.db().await.expect("Unable to get db");
+            self.query_at_snapshot(db.basis_tx(), query).await
         }
 
         async fn query_at_snapshot(&self, snapshot_tx: u64, query: Query) -> Vec<Vec<Value>> {
-            let results = Database::new(snapshot_tx)
-                .query(&self.storage, &self.resolver, query)
+            let results = self
+                .conn
+                .query(&Database::new(snapshot_tx), query)
                 .await
                 .expect("Unable to query");
-            results.filter_map(Result::ok).collect()
+            results.into_iter().filter_map(Result::ok).collect()
         }
 
         async fn try_query(
             &self,
             query: Query,
         ) -> crate::query::Result<Vec<QueryResult<StorageError<'_>>>, StorageError<'_>> {
-            let result = Database::new(self.last_tx)
-                .query(&self.storage, &self.resolver, query)
-                .await?;
-            Ok(result.collect())
+            let db = self.conn.db().await?;
+            self.conn.query(&db, query).await
         }
     }
 
-    fn now() -> Instant {
-        Instant(
-            SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .expect("Time went backwards")
-                .as_secs(),
-        )
-    }
-
     fn create_schema() -> Transaction {
         Transaction::new()
             .with(AttributeDefinition::new("movie/name", ValueType::Str))
@@ -127,6 +129,11 @@ mod tests {
                     .with_doc("A person's email address. Unique across all people!")
                     .unique(),
             )
+            .with(
+                AttributeDefinition::new("person/id", ValueType::Str)
+                    .with_doc("A person's ID in an external system")
+                    .unique_identity(),
+            )
             .with(
                 AttributeDefinition::new("artist/name", ValueType::Str)
                     .with_doc("An artist's name"),
@@ -140,6 +147,59 @@ mod tests {
                     .with_doc("Artists of release")
                     .many(),
             )
+            .with(
+                AttributeDefinition::new("release/tracks", ValueType::Ref)
+                    .with_doc("Tracks of release")
+                    .many()
+                    .component(),
+            )
+            .with(AttributeDefinition::new("track/name", ValueType::Str).with_doc("A track's name"))
+            .with(
+                AttributeDefinition::new("person/friends", ValueType::Ref)
+                    .with_doc("A person's friends, who must exist")
+                    .many()
+                    .verify_refs(),
+            )
+            .with(
+                AttributeDefinition::new("tx/author", ValueType::Str)
+                    .with_doc("Who made the transaction"),
+            )
+    }
+
+    /// Creates Alice, with `attribute` set to `value` on top of her name.
+    async fn create_alice(sut: &mut Sut, attribute: &str, value: impl Into<Value>) -> u64 {
+        let tx_result = sut
+            .transact(
+                Transaction::new().with(
+                    EntityOperation::on_temp_id("alice")
+                        .assert("person/name", "Alice")
+                        .assert(attribute, value),
+                ),
+            )
+            .await;
+        tx_result.temp_ids["alice"]
+    }
+
+    fn born_query(entity: u64) -> Query {
+        // [:find ?born
+        //  :where [entity :person/born ?born]]
+        Query::new().find(Find::variable("?born")).r#where(
+            Clause::new()
+                .with_entity(Pattern::entity(entity))
+                .with_attribute(Pattern::ident("person/born"))
+                .with_value(Pattern::variable("?born")),
+        )
+    }
+
+    fn names_query(attribute: &str) -> Query {
+        // [:find ?name
+        //  :where [?entity attribute ?name]]
+        Query::new().find(Find::variable("?name")).r#where(
+            Clause::new()
+                .with_entity(Pattern::variable("?entity"))
+                .with_attribute(Pattern::ident(attribute))
+                .with_value(Pattern::variable("?name")),
+        )
     }
 
     fn create_beatles() -> Transaction {
@@ -244,6 +304,56 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn reference_attribute_by_id_in_transaction() {
+        let mut sut = Sut::new().await;
+
+        // [:find ?attribute
+        //  :where [?attribute :db/attr/ident "person/name"]]
+        let query_result = sut
+            .query(
+                Query::new().find(Find::variable("?attribute")).r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?attribute"))
+                        .with_attribute(Pattern::ident(DB_ATTR_IDENT_IDENT))
+                        .with_value(Pattern::value("person/name")),
+                ),
+            )
+            .await;
+        let Some(Value::Ref(attribute_id)) = query_result.first().and_then(|row| row.first())
+        else {
+            panic!("Attribute not found");
+        };
+
+        // [{:db/id "joe"
+        //   attribute_id "Joe"}]
+        let tx_result = sut
+            .transact(
+                Transaction::new()
+                    .with(EntityOperation::on_temp_id("joe").assert(*attribute_id, "Joe")),
+            )
+            .await;
+        let joe_id = tx_result.temp_ids["joe"];
+
+        // [:find ?name
+        //  :where [joe_id :person/name ?name]]
+        let query_result = sut
+            .query(
+                Query::new().find(Find::variable("?name")).r#where(
+                    Clause::new()
+                        .with_entity(Pattern::entity(joe_id))
+                        .with_attribute(Pattern::ident("person/name"))
+                        .with_value(Pattern::variable("?name")),
+                ),
+            )
+            .await;
+
+        assert_that!(
+            query_result,
+            unordered_elements_are![elements_are![eq(Value::str("Joe"))]]
+        );
+    }
+
     #[tokio::test]
     async fn reject_transaction_with_invalid_attribute_type() {
         let sut = Sut::new().await;
@@ -363,7 +473,7 @@ mod tests {
             .query(
                 Query::new().find(Find::variable("?email")).r#where(
                     Clause::new()
-                        .with_entity(Pattern::Constant(joe_id))
+                        .with_entity(Pattern::entity(joe_id))
                         .with_attribute(Pattern::ident("person/email"))
                         .with_value(Pattern::variable("?email")),
                 ),
@@ -416,7 +526,7 @@ mod tests {
             .query(
                 Query::new().find(Find::variable("?likes")).r#where(
                     Clause::new()
-                        .with_entity(Pattern::Constant(joe_id))
+                        .with_entity(Pattern::entity(joe_id))
                         .with_attribute(Pattern::ident("person/likes"))
                         .with_value(Pattern::variable("?likes")),
                 ),
@@ -467,10 +577,10 @@ mod tests {
         //  :where [?joe_id :person/likes ?likes]]
         let query_result = sut
             .query_at_snapshot(
-                first_tx_result.tx_id,
+                first_tx_result.db_after.basis_tx(),
                 Query::new().find(Find::variable("?likes")).r#where(
                     Clause::new()
-                        .with_entity(Pattern::Constant(joe_id))
+                        .with_entity(Pattern::entity(joe_id))
                         .with_attribute(Pattern::ident("person/likes"))
                         .with_value(Pattern::variable("?likes")),
                 ),
@@ -520,7 +630,7 @@ mod tests {
         assert_that!(
             query_result,
             unordered_elements_are![elements_are![
-                eq(Value::Ref(tx_result.tx_id)),
+                eq(Value::Ref(tx_result.db_after.basis_tx())),
                 matches_pattern!(Value::U64(gt(0))),
             ]]
         );
@@ -551,7 +661,7 @@ mod tests {
                             .with_entity(Pattern::variable("?e"))
                             .with_attribute(Pattern::variable("?a"))
                             .with_value(Pattern::variable("?v"))
-                            .with_tx(Pattern::Constant(tx_result.tx_id)),
+                            .with_tx(Pattern::Constant(tx_result.db_after.basis_tx())),
                     ),
             )
             .await;
@@ -563,7 +673,7 @@ mod tests {
                 elements_are![anything(), anything(), eq(Value::str("Joe"))],
                 // tx time datom
                 elements_are![
-                    eq(Value::Ref(tx_result.tx_id)),
+                    eq(Value::Ref(tx_result.db_after.basis_tx())),
                     eq(Value::Ref(DB_TX_TIME_ID)),
                     anything()
                 ]
@@ -946,7 +1056,7 @@ mod tests {
         //  :where [?joe_id :person/likes ?likes]]
         let query = Query::new().find(Find::variable("?likes")).r#where(
             Clause::new()
-                .with_entity(Pattern::Constant(joe_id))
+                .with_entity(Pattern::entity(joe_id))
                 .with_attribute(Pattern::ident("person/likes"))
                 .with_value(Pattern::variable("?likes")),
         );
@@ -1018,4 +1128,2543 @@ mod tests {
             assert!(tx_result.is_none());
         }
     }
+
+    mod compare_and_swap {
+        use super::*;
+
+        #[tokio::test]
+        async fn swap_value_when_current_value_matches() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/born", 1970).await;
+
+            sut.transact(Transaction::new().with(
+                EntityOperation::on_id(alice_id).compare_and_swap("person/born", 1970, 1971),
+            ))
+            .await;
+
+            assert_that!(
+                sut.query(born_query(alice_id)).await,
+                unordered_elements_are![elements_are![eq(Value::I64(1971))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_transaction_when_current_value_differs() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/born", 1970).await;
+
+            let tx_result = sut
+                .try_transact(Transaction::new().with(
+                    EntityOperation::on_id(alice_id).compare_and_swap("person/born", 1969, 1971),
+                ))
+                .await;
+
+            assert!(tx_result.is_none());
+            assert_that!(
+                sut.query(born_query(alice_id)).await,
+                unordered_elements_are![elements_are![eq(Value::I64(1970))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn swap_value_when_expecting_no_current_value() {
+            let mut sut = Sut::new().await;
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("bob").assert("person/name", "Bob")),
+                )
+                .await;
+            let bob_id = tx_result.temp_ids["bob"];
+
+            sut.transact(
+                Transaction::new().with(EntityOperation::on_id(bob_id).compare_and_swap(
+                    "person/born",
+                    Value::Nil,
+                    1980,
+                )),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(born_query(bob_id)).await,
+                unordered_elements_are![elements_are![eq(Value::I64(1980))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn compare_with_value_retracted_in_same_transaction() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/born", 1970).await;
+
+            let tx_result = sut
+                .try_transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_id(alice_id).retract("person/born", 1970))
+                        .with(EntityOperation::on_id(alice_id).compare_and_swap(
+                            "person/born",
+                            1970,
+                            1971,
+                        )),
+                )
+                .await;
+            assert!(tx_result.is_none());
+
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_id(alice_id).retract("person/born", 1970))
+                    .with(EntityOperation::on_id(alice_id).compare_and_swap(
+                        "person/born",
+                        Value::Nil,
+                        1971,
+                    )),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(born_query(alice_id)).await,
+                unordered_elements_are![elements_are![eq(Value::I64(1971))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_cardinality_many_attribute() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            let tx_result = sut
+                .conn
+                .transact(Transaction::new().with(
+                    EntityOperation::on_id(alice_id).compare_and_swap(
+                        "person/likes",
+                        "Pizza",
+                        "Beer",
+                    ),
+                ))
+                .await;
+
+            assert!(matches!(
+                tx_result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::CompareAndSwapCardinalityMany(_)
+                ))
+            ));
+        }
+    }
+
+    mod retract_entity {
+        use super::*;
+
+        #[tokio::test]
+        async fn retract_all_attributes_of_entity() {
+            let mut sut = Sut::new().await;
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(
+                            EntityOperation::on_temp_id("alice")
+                                .assert("person/name", "Alice")
+                                .assert("person/likes", "Pizza")
+                                .assert("person/likes", "Beer"),
+                        )
+                        .with(EntityOperation::on_new().assert("person/name", "Bob")),
+                )
+                .await;
+            let alice_id = tx_result.temp_ids["alice"];
+
+            sut.transact(Transaction::new().retract_entity(alice_id))
+                .await;
+
+            assert_that!(
+                sut.query(names_query("person/name")).await,
+                unordered_elements_are![elements_are![eq(Value::str("Bob"))]]
+            );
+            assert_that!(sut.query(names_query("person/likes")).await, empty());
+        }
+
+        #[tokio::test]
+        async fn retract_references_to_entity() {
+            let mut sut = Sut::new().await;
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("john").assert("artist/name", "John"))
+                        .with(EntityOperation::on_temp_id("paul").assert("artist/name", "Paul"))
+                        .with(
+                            EntityOperation::on_new()
+                                .assert("release/name", "Abbey Road")
+                                .set_reference("release/artists", "john")
+                                .set_reference("release/artists", "paul"),
+                        ),
+                )
+                .await;
+            let john_id = tx_result.temp_ids["john"];
+            let paul_id = tx_result.temp_ids["paul"];
+
+            sut.transact(Transaction::new().retract_entity(john_id))
+                .await;
+
+            assert_that!(
+                sut.query(names_query("release/artists")).await,
+                unordered_elements_are![elements_are![eq(Value::Ref(paul_id))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn retract_components_recursively() {
+            let mut sut = Sut::new().await;
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("john").assert("artist/name", "John"))
+                        .with(
+                            EntityOperation::on_temp_id("track")
+                                .assert("track/name", "Come Together"),
+                        )
+                        .with(
+                            EntityOperation::on_temp_id("release")
+                                .assert("release/name", "Abbey Road")
+                                .set_reference("release/artists", "john")
+                                .set_reference("release/tracks", "track"),
+                        ),
+                )
+                .await;
+            let release_id = tx_result.temp_ids["release"];
+
+            sut.transact(Transaction::new().retract_entity(release_id))
+                .await;
+
+            assert_that!(sut.query(names_query("release/name")).await, empty());
+            assert_that!(sut.query(names_query("track/name")).await, empty());
+            // Artists are only referenced by the release, they are not its components.
+            assert_that!(
+                sut.query(names_query("artist/name")).await,
+                unordered_elements_are![elements_are![eq(Value::str("John"))]]
+            );
+        }
+    }
+
+    mod upsert {
+        use super::*;
+
+        fn person_query() -> Query {
+            // [:find ?person ?name
+            //  :where [?person :person/name ?name]]
+            Query::new()
+                .find(Find::variable("?person"))
+                .find(Find::variable("?name"))
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?person"))
+                        .with_attribute(Pattern::ident("person/name"))
+                        .with_value(Pattern::variable("?name")),
+                )
+        }
+
+        #[tokio::test]
+        async fn resolve_temp_id_to_existing_entity() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/id", "p-1").await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(
+                            EntityOperation::on_temp_id("person")
+                                .assert("person/id", "p-1")
+                                .assert("person/name", "Alicia"),
+                        )
+                        .with(
+                            EntityOperation::on_new()
+                                .assert("release/name", "Abbey Road")
+                                .set_reference("release/artists", "person"),
+                        ),
+                )
+                .await;
+
+            assert_eq!(alice_id, tx_result.temp_ids["person"]);
+            assert_that!(
+                sut.query(person_query()).await,
+                unordered_elements_are![elements_are![
+                    eq(Value::Ref(alice_id)),
+                    eq(Value::str("Alicia"))
+                ]]
+            );
+        }
+
+        #[tokio::test]
+        async fn resolve_new_entity_to_existing_entity() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/id", "p-1").await;
+
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_new()
+                        .assert("person/id", "p-1")
+                        .assert("person/name", "Alicia"),
+                ),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(person_query()).await,
+                unordered_elements_are![elements_are![
+                    eq(Value::Ref(alice_id)),
+                    eq(Value::str("Alicia"))
+                ]]
+            );
+        }
+
+        #[tokio::test]
+        async fn create_new_entity_for_new_identity() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/id", "p-1").await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("bob")
+                            .assert("person/id", "p-2")
+                            .assert("person/name", "Bob"),
+                    ),
+                )
+                .await;
+
+            assert_ne!(alice_id, tx_result.temp_ids["bob"]);
+            assert_eq!(2, sut.query(person_query()).await.len());
+        }
+    }
+
+    mod lookup_ref {
+        use super::*;
+        use crate::storage::lookup_ref::LookupRefError;
+
+        fn name_query(entity: Pattern<EntityIdentifier>) -> Query {
+            // [:find ?name
+            //  :where [entity :person/name ?name]]
+            Query::new().find(Find::variable("?name")).r#where(
+                Clause::new()
+                    .with_entity(entity)
+                    .with_attribute(Pattern::ident("person/name"))
+                    .with_value(Pattern::variable("?name")),
+            )
+        }
+
+        #[tokio::test]
+        async fn update_entity_identified_by_lookup_ref() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/email", "alice@example.com").await;
+
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_lookup_ref("person/email", "alice@example.com")
+                        .assert("person/name", "Alicia"),
+                ),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(name_query(Pattern::entity(alice_id))).await,
+                unordered_elements_are![elements_are![eq(Value::str("Alicia"))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reference_entity_identified_by_lookup_ref() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/email", "alice@example.com").await;
+
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_new()
+                        .assert("movie/name", "Alice in Wonderland")
+                        .set_lookup_ref(
+                            "movie/cast",
+                            LookupRef::new("person/email", "alice@example.com"),
+                        ),
+                ),
+            )
+            .await;
+
+            // [:find ?cast
+            //  :where [?movie :movie/cast ?cast]]
+            let query = Query::new().find(Find::variable("?cast")).r#where(
+                Clause::new()
+                    .with_entity(Pattern::variable("?movie"))
+                    .with_attribute(Pattern::ident("movie/cast"))
+                    .with_value(Pattern::variable("?cast")),
+            );
+            assert_that!(
+                sut.query(query).await,
+                unordered_elements_are![elements_are![eq(Value::Ref(alice_id))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn query_entity_identified_by_lookup_ref() {
+            let mut sut = Sut::new().await;
+            create_alice(&mut sut, "person/email", "alice@example.com").await;
+
+            let query = name_query(Pattern::lookup_ref("person/email", "alice@example.com"));
+
+            assert_that!(
+                sut.query(query).await,
+                unordered_elements_are![elements_are![eq(Value::str("Alice"))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_transaction_when_lookup_ref_is_not_found() {
+            let sut = Sut::new().await;
+
+            let tx_result = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_lookup_ref("person/email", "bob@example.com")
+                            .assert("person/name", "Bob"),
+                    ),
+                )
+                .await;
+
+            assert!(tx_result.is_none());
+        }
+
+        #[tokio::test]
+        async fn fail_query_when_lookup_ref_is_not_found() {
+            let mut sut = Sut::new().await;
+            create_alice(&mut sut, "person/email", "alice@example.com").await;
+
+            let query_result = sut
+                .try_query(name_query(Pattern::lookup_ref(
+                    "person/email",
+                    "bob@example.com",
+                )))
+                .await;
+
+            assert!(matches!(
+                query_result,
+                Err(QueryError::LookupRefError(LookupRefError::NotFound(_)))
+            ));
+        }
+
+        #[tokio::test]
+        async fn fail_query_when_lookup_ref_attribute_is_not_unique() {
+            let mut sut = Sut::new().await;
+            create_alice(&mut sut, "person/email", "alice@example.com").await;
+
+            let query_result = sut
+                .try_query(name_query(Pattern::lookup_ref("person/name", "Alice")))
+                .await;
+
+            assert!(matches!(
+                query_result,
+                Err(QueryError::LookupRefError(LookupRefError::NotUnique(_)))
+            ));
+        }
+    }
+
+    mod transaction_functions {
+        use async_trait::async_trait;
+
+        use crate::tx::function::*;
+
+        use super::*;
+
+        /// Adds an amount to the current value of an `I64` attribute.
+        struct Increment;
+
+        #[async_trait]
+        impl TransactionFunction<InMemoryStorage> for Increment {
+            async fn apply(
+                &self,
+                db: &DatabaseView<'_, InMemoryStorage>,
+                args: &[Value],
+            ) -> Result<Vec<EntityOperation>, FunctionError> {
+                let [Value::U64(entity), Value::Str(attribute), Value::I64(amount)] = args else {
+                    return Err("expected entity, attribute and amount".into());
+                };
+                // [:find ?value :where [entity attribute ?value]]
+                let query = Query::new().find(Find::variable("?value")).r#where(
+                    Clause::new()
+                        .with_entity(Pattern::entity(*entity))
+                        .with_attribute(Pattern::ident(attribute))
+                        .with_value(Pattern::variable("?value")),
+                );
+                let mut current = 0;
+                for result in db.query(query).await? {
+                    if let [Value::I64(value)] = result?[..] {
+                        current = value;
+                    }
+                }
+                Ok(vec![
+                    EntityOperation::on_id(*entity).assert(attribute.as_str(), current + amount)
+                ])
+            }
+        }
+
+        async fn create_sut() -> Sut {
+            Sut::with_functions(TransactionFunctions::new().with("increment", Increment)).await
+        }
+
+        #[tokio::test]
+        async fn expand_function_into_operations() {
+            let mut sut = create_sut().await;
+            let alice_id = create_alice(&mut sut, "person/born", 1970).await;
+
+            sut.transact(Transaction::new().call(
+                "increment",
+                vec![
+                    Value::U64(alice_id),
+                    Value::str("person/born"),
+                    Value::I64(5),
+                ],
+            ))
+            .await;
+
+            assert_that!(
+                sut.query(born_query(alice_id)).await,
+                unordered_elements_are![elements_are![eq(Value::I64(1975))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn read_latest_value_in_function() {
+            let mut sut = create_sut().await;
+            let alice_id = create_alice(&mut sut, "person/born", 1970).await;
+
+            let args = vec![
+                Value::U64(alice_id),
+                Value::str("person/born"),
+                Value::I64(1),
+            ];
+            sut.transact(Transaction::new().call("increment", args.clone()))
+                .await;
+            sut.transact(Transaction::new().call("increment", args))
+                .await;
+
+            assert_that!(
+                sut.query(born_query(alice_id)).await,
+                unordered_elements_are![elements_are![eq(Value::I64(1972))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_transaction_with_unknown_function() {
+            let mut sut = create_sut().await;
+            let alice_id = create_alice(&mut sut, "person/born", 1970).await;
+
+            let tx_result = sut
+                .conn
+                .transact(Transaction::new().call("decrement", vec![Value::U64(alice_id)]))
+                .await;
+
+            assert!(tx_result.is_err_and(|err| matches!(
+                err,
+                ConnectionError::TransactionError(TransactionError::FunctionNotFound(name))
+                    if name == "decrement"
+            )));
+        }
+
+        #[tokio::test]
+        async fn reject_transaction_when_function_fails() {
+            let mut sut = create_sut().await;
+            let alice_id = create_alice(&mut sut, "person/born", 1970).await;
+
+            let tx_result = sut
+                .try_transact(Transaction::new().call("increment", vec![Value::U64(alice_id)]))
+                .await;
+
+            assert!(tx_result.is_none());
+            assert_that!(
+                sut.query(born_query(alice_id)).await,
+                unordered_elements_are![elements_are![eq(Value::I64(1970))]]
+            );
+        }
+    }
+
+    mod nested_entities {
+        use super::*;
+
+        fn tracks_query(release: &str) -> Query {
+            // [:find ?track-name
+            //  :where [?release :release/name release]
+            //         [?release :release/tracks ?track]
+            //         [?track :track/name ?track-name]]
+            Query::new()
+                .find(Find::variable("?track-name"))
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?release"))
+                        .with_attribute(Pattern::ident("release/name"))
+                        .with_value(Pattern::value(release)),
+                )
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?release"))
+                        .with_attribute(Pattern::ident("release/tracks"))
+                        .with_value(Pattern::variable("?track")),
+                )
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?track"))
+                        .with_attribute(Pattern::ident("track/name"))
+                        .with_value(Pattern::variable("?track-name")),
+                )
+        }
+
+        fn artists_query(release: &str) -> Query {
+            // [:find ?artist-name
+            //  :where [?release :release/name release]
+            //         [?release :release/artists ?artist]
+            //         [?artist :artist/name ?artist-name]]
+            Query::new()
+                .find(Find::variable("?artist-name"))
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?release"))
+                        .with_attribute(Pattern::ident("release/name"))
+                        .with_value(Pattern::value(release)),
+                )
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?release"))
+                        .with_attribute(Pattern::ident("release/artists"))
+                        .with_value(Pattern::variable("?artist")),
+                )
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?artist"))
+                        .with_attribute(Pattern::ident("artist/name"))
+                        .with_value(Pattern::variable("?artist-name")),
+                )
+        }
+
+        #[tokio::test]
+        async fn create_nested_entities() {
+            let mut sut = Sut::new().await;
+
+            // [{:release/name "Abbey Road"
+            //   :release/tracks [{:track/name "Come Together"}
+            //                    {:track/name "Something"}]}]
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("release")
+                            .assert("release/name", "Abbey Road")
+                            .set_entity(
+                                "release/tracks",
+                                EntityOperation::on_new().assert("track/name", "Come Together"),
+                            )
+                            .set_entity(
+                                "release/tracks",
+                                EntityOperation::on_new().assert("track/name", "Something"),
+                            ),
+                    ),
+                )
+                .await;
+
+            assert_that!(
+                sut.query(tracks_query("Abbey Road")).await,
+                unordered_elements_are![
+                    elements_are![eq(Value::str("Come Together"))],
+                    elements_are![eq(Value::str("Something"))],
+                ]
+            );
+            // Only temp IDs given by the caller are reported.
+            assert_eq!(
+                vec!["release"],
+                tx_result.temp_ids.keys().collect::<Vec<_>>()
+            );
+        }
+
+        #[tokio::test]
+        async fn reference_nested_entity_by_temp_id() {
+            let mut sut = Sut::new().await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("release/name", "Abbey Road")
+                            .set_entity(
+                                "release/tracks",
+                                EntityOperation::on_temp_id("track")
+                                    .assert("track/name", "Come Together"),
+                            ),
+                    ),
+                )
+                .await;
+
+            let track_id = tx_result.temp_ids["track"];
+            // [:find ?release :where [?release :release/tracks track]]
+            let query = Query::new().find(Find::variable("?release")).r#where(
+                Clause::new()
+                    .with_entity(Pattern::variable("?release"))
+                    .with_attribute(Pattern::ident("release/tracks"))
+                    .with_value(Pattern::value(Value::Ref(track_id))),
+            );
+            assert_eq!(1, sut.query(query).await.len());
+        }
+
+        #[tokio::test]
+        async fn assert_reverse_reference() {
+            let mut sut = Sut::new().await;
+
+            // [{:db/id "release" :release/name "Abbey Road"}
+            //  {:artist/name "John" :release/_artists "release"}
+            //  {:artist/name "Paul" :release/_artists "release"}]
+            sut.transact(
+                Transaction::new()
+                    .with(
+                        EntityOperation::on_temp_id("release").assert("release/name", "Abbey Road"),
+                    )
+                    .with(
+                        EntityOperation::on_new()
+                            .assert("artist/name", "John")
+                            .set_reference("release/_artists", "release"),
+                    )
+                    .with(
+                        EntityOperation::on_temp_id("paul")
+                            .assert("artist/name", "Paul")
+                            .set_reference("release/_artists", "release"),
+                    ),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(artists_query("Abbey Road")).await,
+                unordered_elements_are![
+                    elements_are![eq(Value::str("John"))],
+                    elements_are![eq(Value::str("Paul"))],
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn assert_reverse_reference_to_nested_entity() {
+            let mut sut = Sut::new().await;
+
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_new()
+                        .assert("track/name", "Come Together")
+                        .set_entity(
+                            "release/_tracks",
+                            EntityOperation::on_new().assert("release/name", "Abbey Road"),
+                        ),
+                ),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(tracks_query("Abbey Road")).await,
+                unordered_elements_are![elements_are![eq(Value::str("Come Together"))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_reverse_attribute_with_non_reference_value() {
+            let sut = Sut::new().await;
+
+            let tx_result = sut
+                .try_transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("release/_artists", "Abbey Road")),
+                )
+                .await;
+
+            assert!(tx_result.is_none());
+        }
+    }
+
+    mod redundant_datoms {
+        use super::*;
+
+        #[tokio::test]
+        async fn drop_assertion_of_current_value() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_id(alice_id).assert("person/name", "Alice")),
+                )
+                .await;
+
+            assert!(tx_result.is_empty());
+            assert_eq!(1, tx_result.redundant.len());
+            assert_eq!(Value::str("Alice"), tx_result.redundant[0].value);
+        }
+
+        #[tokio::test]
+        async fn drop_assertion_of_existing_value_with_cardinality_many() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_id(alice_id)
+                            .assert("person/likes", "Pizza")
+                            .assert("person/likes", "Beer"),
+                    ),
+                )
+                .await;
+
+            assert!(!tx_result.is_empty());
+            let values: Vec<_> = tx_result
+                .tx_data
+                .iter()
+                .filter(|datom| datom.entity == alice_id)
+                .map(|datom| &datom.value)
+                .collect();
+            assert_eq!(vec![&Value::str("Beer")], values);
+            assert_eq!(1, tx_result.redundant.len());
+        }
+
+        #[tokio::test]
+        async fn drop_retraction_of_missing_value() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_id(alice_id).retract("person/likes", "Beer")),
+                )
+                .await;
+
+            assert!(tx_result.is_empty());
+            assert_eq!(1, tx_result.redundant.len());
+        }
+
+        #[tokio::test]
+        async fn drop_duplicate_assertions_within_transaction() {
+            let mut sut = Sut::new().await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("alice")
+                            .assert("person/likes", "Pizza")
+                            .assert("person/likes", "Pizza"),
+                    ),
+                )
+                .await;
+
+            let alice_id = tx_result.temp_ids["alice"];
+            let asserted = tx_result
+                .tx_data
+                .iter()
+                .filter(|datom| datom.entity == alice_id)
+                .count();
+            assert_eq!(1, asserted);
+            assert_eq!(1, tx_result.redundant.len());
+        }
+
+        #[tokio::test]
+        async fn retract_replaced_value_once() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_id(alice_id)
+                            .retract("person/name", "Alice")
+                            .assert("person/name", "Alicia"),
+                    ),
+                )
+                .await;
+
+            let retracted = tx_result
+                .tx_data
+                .iter()
+                .filter(|datom| datom.op == Op::Retract)
+                .map(|datom| &datom.value)
+                .collect::<Vec<_>>();
+            assert_eq!(vec![&Value::str("Alice")], retracted);
+        }
+
+        #[tokio::test]
+        async fn reject_retraction_and_assertion_of_same_value() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            let tx_result = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_id(alice_id)
+                            .retract("person/likes", "Pizza")
+                            .assert("person/likes", "Pizza"),
+                    ),
+                )
+                .await;
+
+            assert!(tx_result.is_none());
+        }
+
+        #[tokio::test]
+        async fn reject_assertion_and_retraction_of_same_value() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            for value in ["Pizza", "Beer"] {
+                let tx_result = sut
+                    .try_transact(
+                        Transaction::new().with(
+                            EntityOperation::on_id(alice_id)
+                                .assert("person/likes", value)
+                                .retract("person/likes", value),
+                        ),
+                    )
+                    .await;
+
+                assert!(tx_result.is_none());
+            }
+        }
+
+        #[tokio::test]
+        async fn retract_entity_values_once() {
+            let mut sut = Sut::new().await;
+            let alice_id = create_alice(&mut sut, "person/likes", "Pizza").await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_id(alice_id).retract("person/likes", "Pizza"))
+                        .retract_entity(alice_id),
+                )
+                .await;
+
+            let retracted = tx_result
+                .tx_data
+                .iter()
+                .filter(|datom| datom.op == Op::Retract)
+                .count();
+            assert_eq!(2, retracted);
+        }
+    }
+
+    mod referential_integrity {
+        use super::*;
+
+        const MISSING_ENTITY: u64 = 1_000_000;
+
+        #[tokio::test]
+        async fn reject_reference_to_missing_entity() {
+            let sut = Sut::new().await;
+
+            let tx_result = sut
+                .conn
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("person/name", "Alice")
+                            .assert("person/friends", Value::Ref(MISSING_ENTITY)),
+                    ),
+                )
+                .await;
+
+            assert!(tx_result.is_err_and(|err| matches!(
+                err,
+                ConnectionError::TransactionError(TransactionError::MissingReference {
+                    target: MISSING_ENTITY,
+                    ..
+                })
+            )));
+        }
+
+        #[tokio::test]
+        async fn accept_reference_to_existing_entity() {
+            let mut sut = Sut::new().await;
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("bob").assert("person/name", "Bob")),
+                )
+                .await;
+            let bob_id = tx_result.temp_ids["bob"];
+
+            let tx_result = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("person/name", "Alice")
+                            .assert("person/friends", Value::Ref(bob_id)),
+                    ),
+                )
+                .await;
+
+            assert!(tx_result.is_some());
+        }
+
+        #[tokio::test]
+        async fn accept_reference_to_entity_created_in_transaction() {
+            let sut = Sut::new().await;
+
+            let tx_result = sut
+                .try_transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("bob").assert("person/name", "Bob"))
+                        .with(
+                            EntityOperation::on_new()
+                                .assert("person/name", "Alice")
+                                .set_reference("person/friends", "bob"),
+                        ),
+                )
+                .await;
+
+            assert!(tx_result.is_some());
+        }
+
+        #[tokio::test]
+        async fn accept_missing_reference_of_unverified_attribute() {
+            let sut = Sut::new().await;
+
+            let tx_result = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("release/name", "Abbey Road")
+                            .assert("release/artists", Value::Ref(MISSING_ENTITY)),
+                    ),
+                )
+                .await;
+
+            assert!(tx_result.is_some());
+        }
+    }
+
+    mod tx_metadata {
+        use crate::clock::Instant;
+
+        use super::*;
+
+        #[tokio::test]
+        async fn query_entities_by_transaction_metadata() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_new().assert("person/name", "Alice"))
+                    .with(EntityOperation::on_tx().assert("tx/author", "admin")),
+            )
+            .await;
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_new().assert("person/name", "Bob"))
+                    .with(EntityOperation::on_tx().assert("tx/author", "importer")),
+            )
+            .await;
+
+            // [:find ?name
+            //  :where [_ :person/name ?name ?tx]
+            //         [?tx :tx/author "admin"]]
+            let query_result = sut
+                .query(
+                    Query::new()
+                        .find(Find::variable("?name"))
+                        .r#where(
+                            Clause::new()
+                                .with_attribute(Pattern::ident("person/name"))
+                                .with_value(Pattern::variable("?name"))
+                                .with_tx(Pattern::variable("?tx")),
+                        )
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?tx"))
+                                .with_attribute(Pattern::ident("tx/author"))
+                                .with_value(Pattern::value("admin")),
+                        ),
+                )
+                .await;
+
+            assert_that!(
+                query_result,
+                unordered_elements_are![elements_are![eq(Value::str("Alice"))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reference_transaction_entity() {
+            let mut sut = Sut::new().await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("release")
+                            .assert("release/name", "Abbey Road")
+                            .set_reference("release/artists", TX_TEMP_ID),
+                    ),
+                )
+                .await;
+
+            let tx_id = tx_result.db_after.basis_tx();
+            let release_id = tx_result.temp_ids["release"];
+            assert!(tx_result.tx_data.contains(&Datom::add(
+                release_id,
+                release_artists_id(&sut).await,
+                Value::Ref(tx_id),
+                tx_id
+            )));
+            assert!(!tx_result.temp_ids.contains_key(TX_TEMP_ID));
+        }
+
+        async fn release_artists_id(sut: &Sut) -> u64 {
+            let storage = sut.conn.storage().await;
+            let attribute = sut
+                .conn
+                .resolver()
+                .resolve(&*storage, "release/artists", u64::MAX)
+                .await
+                .expect("Unable to resolve attribute");
+            attribute.id
+        }
+
+        #[tokio::test]
+        async fn override_transaction_time() {
+            let mut sut = Sut::new().await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/name", "Alice"))
+                        .with_tx_time(Instant(42)),
+                )
+                .await;
+
+            let tx_id = tx_result.db_after.basis_tx();
+            assert!(tx_result
+                .tx_data
+                .contains(&Datom::add(tx_id, DB_TX_TIME_ID, 42u64, tx_id)));
+        }
+    }
+
+    mod schema_alteration {
+        use super::*;
+
+        fn is_invalid_alteration(
+            result: &Result<
+                TransactionReport,
+                ConnectionError<StorageError<'static>, std::convert::Infallible>,
+            >,
+        ) -> bool {
+            matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::InvalidAlteration { .. }
+                ))
+            )
+        }
+
+        #[tokio::test]
+        async fn rename_attribute() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new().with(EntityOperation::on_new().assert("artist/name", "John")),
+            )
+            .await;
+
+            sut.transact(
+                Transaction::new()
+                    .with(AttributeAlteration::new("artist/name").rename("artist/full-name")),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(names_query("artist/full-name")).await,
+                unordered_elements_are![elements_are![eq(Value::str("John"))]]
+            );
+            // Previous ident still resolves to the renamed attribute.
+            sut.transact(
+                Transaction::new().with(EntityOperation::on_new().assert("artist/name", "Paul")),
+            )
+            .await;
+            assert_that!(
+                sut.query(names_query("artist/full-name")).await,
+                unordered_elements_are![
+                    elements_are![eq(Value::str("John"))],
+                    elements_are![eq(Value::str("Paul"))],
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn change_cardinality_to_many() {
+            let mut sut = Sut::new().await;
+            let tx_result = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("john").assert("artist/name", "John")),
+                )
+                .await;
+            let john_id = tx_result.temp_ids["john"];
+
+            sut.transact(Transaction::new().with(AttributeAlteration::new("artist/name").many()))
+                .await;
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_id(john_id).assert("artist/name", "John Lennon")),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(names_query("artist/name")).await,
+                unordered_elements_are![
+                    elements_are![eq(Value::str("John"))],
+                    elements_are![eq(Value::str("John Lennon"))],
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_cardinality_one_when_entities_have_multiple_values() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_new()
+                        .assert("person/likes", "Pizza")
+                        .assert("person/likes", "Beer"),
+                ),
+            )
+            .await;
+
+            let result = sut
+                .conn
+                .transact(Transaction::new().with(AttributeAlteration::new("person/likes").one()))
+                .await;
+
+            assert!(is_invalid_alteration(&result));
+        }
+
+        #[tokio::test]
+        async fn make_attribute_unique_when_values_are_unique() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_new().assert("artist/name", "John"))
+                    .with(EntityOperation::on_new().assert("artist/name", "Paul")),
+            )
+            .await;
+
+            sut.transact(Transaction::new().with(AttributeAlteration::new("artist/name").unique()))
+                .await;
+
+            let result = sut
+                .try_transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("artist/name", "John")),
+                )
+                .await;
+            assert!(result.is_none());
+        }
+
+        #[tokio::test]
+        async fn reject_unique_when_values_are_not_unique() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_new().assert("artist/name", "John"))
+                    .with(EntityOperation::on_new().assert("artist/name", "John")),
+            )
+            .await;
+
+            let result = sut
+                .conn
+                .transact(Transaction::new().with(AttributeAlteration::new("artist/name").unique()))
+                .await;
+
+            assert!(is_invalid_alteration(&result));
+        }
+
+        #[tokio::test]
+        async fn remove_uniqueness() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_new().assert("person/email", "foo@bar.com")),
+            )
+            .await;
+
+            sut.transact(
+                Transaction::new().with(AttributeAlteration::new("person/email").not_unique()),
+            )
+            .await;
+
+            let result = sut
+                .try_transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/email", "foo@bar.com")),
+                )
+                .await;
+            assert!(result.is_some());
+        }
+    }
+
+    mod value_types {
+        use uuid::Uuid;
+
+        use super::*;
+
+        fn create_device_schema() -> Transaction {
+            Transaction::new()
+                .with(AttributeDefinition::new("device/id", ValueType::Uuid).unique_identity())
+                .with(AttributeDefinition::new("device/active", ValueType::Bool))
+                .with(AttributeDefinition::new("device/kind", ValueType::Keyword))
+                .with(AttributeDefinition::new("device/seen", ValueType::Instant))
+                .with(AttributeDefinition::new("device/load", ValueType::Double))
+                .with(AttributeDefinition::new(
+                    "device/firmware",
+                    ValueType::Bytes,
+                ))
+                .with(AttributeDefinition::new("device/serial", ValueType::BigInt))
+        }
+
+        #[tokio::test]
+        async fn query_by_typed_values() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_device_schema()).await;
+            let id = Uuid::from_u128(0xf81d4fae_7dec_11d0_a765_00a0c91e6bf6);
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_new()
+                        .assert("device/id", id)
+                        .assert("device/active", true)
+                        .assert("device/kind", Value::keyword("kind/sensor"))
+                        .assert("device/seen", Value::Instant(-1000))
+                        .assert("device/load", 0.75),
+                ),
+            )
+            .await;
+
+            // [:find ?id ?seen ?load
+            //  :where [?device :device/id ?id]
+            //         [?device :device/active true]
+            //         [?device :device/kind :kind/sensor]
+            //         [?device :device/seen ?seen]
+            //         [?device :device/load ?load]]
+            let query_result = sut
+                .query(
+                    Query::new()
+                        .find(Find::variable("?id"))
+                        .find(Find::variable("?seen"))
+                        .find(Find::variable("?load"))
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?device"))
+                                .with_attribute(Pattern::ident("device/id"))
+                                .with_value(Pattern::variable("?id")),
+                        )
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?device"))
+                                .with_attribute(Pattern::ident("device/active"))
+                                .with_value(Pattern::value(true)),
+                        )
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?device"))
+                                .with_attribute(Pattern::ident("device/kind"))
+                                .with_value(Pattern::value(Value::keyword("kind/sensor"))),
+                        )
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?device"))
+                                .with_attribute(Pattern::ident("device/seen"))
+                                .with_value(Pattern::variable("?seen")),
+                        )
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?device"))
+                                .with_attribute(Pattern::ident("device/load"))
+                                .with_value(Pattern::variable("?load")),
+                        ),
+                )
+                .await;
+
+            assert_that!(
+                query_result,
+                unordered_elements_are![elements_are![
+                    eq(Value::Uuid(id)),
+                    eq(Value::Instant(-1000)),
+                    eq(Value::from(0.75))
+                ]]
+            );
+        }
+
+        #[tokio::test]
+        async fn query_by_binary_and_big_int_values() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_device_schema()).await;
+            let serial = BigInt::from(i128::MAX);
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_new()
+                        .assert("device/firmware", vec![0x00, 0xca, 0xfe])
+                        .assert("device/serial", serial.clone()),
+                ),
+            )
+            .await;
+
+            // [:find ?firmware
+            //  :where [?device :device/serial 170141183460469231731687303715884105727]
+            //         [?device :device/firmware ?firmware]]
+            let query_result = sut
+                .query(
+                    Query::new()
+                        .find(Find::variable("?firmware"))
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?device"))
+                                .with_attribute(Pattern::ident("device/serial"))
+                                .with_value(Pattern::value(serial)),
+                        )
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?device"))
+                                .with_attribute(Pattern::ident("device/firmware"))
+                                .with_value(Pattern::variable("?firmware")),
+                        ),
+                )
+                .await;
+
+            assert_that!(
+                query_result,
+                unordered_elements_are![elements_are![eq(Value::Bytes(vec![0x00, 0xca, 0xfe]))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_value_larger_than_max_size() {
+            let config = TransactorConfig::new().with_max_value_size(128);
+            let mut sut = Sut::with_config(TransactionFunctions::new(), config).await;
+            sut.transact(create_device_schema()).await;
+
+            let result = sut
+                .conn
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("device/firmware", vec![42; 256])),
+                )
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::ValueTooLarge { max_size: 128, .. }
+                ))
+            ));
+        }
+
+        #[tokio::test]
+        async fn reject_string_longer_than_default_max_size() {
+            let sut = Sut::new().await;
+
+            let result = sut
+                .try_transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/name", "a".repeat(70_000))),
+                )
+                .await;
+
+            assert!(result.is_none());
+        }
+
+        #[tokio::test]
+        async fn reject_string_longer_than_encoding_under_raised_max_size() {
+            let config = TransactorConfig::new().with_max_value_size(1 << 20);
+            let sut = Sut::with_config(TransactionFunctions::new(), config).await;
+
+            let result = sut
+                .conn
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("person/name", "a".repeat(u16::MAX as usize + 1)),
+                    ),
+                )
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::ValueNotEncodable(_)
+                ))
+            ));
+        }
+
+        #[tokio::test]
+        async fn reject_value_of_wrong_type() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_device_schema()).await;
+
+            let result = sut
+                .try_transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("device/active", 1u64)),
+                )
+                .await;
+
+            assert!(result.is_none());
+        }
+    }
+
+    mod tuples {
+        use super::*;
+
+        fn create_album_schema() -> Transaction {
+            Transaction::new()
+                .with(AttributeDefinition::new("album/artist", ValueType::Str))
+                .with(AttributeDefinition::new("album/name", ValueType::Str))
+                .with(
+                    AttributeDefinition::composite_tuple(
+                        "album/artist+name",
+                        &["album/artist", "album/name"],
+                    )
+                    .unique(),
+                )
+                .with(AttributeDefinition::homogeneous_tuple(
+                    "album/ratings",
+                    ValueType::I64,
+                ))
+                .with(AttributeDefinition::heterogeneous_tuple(
+                    "album/chart",
+                    &[ValueType::Str, ValueType::I64],
+                ))
+        }
+
+        fn artist_and_name(artist: &str, name: &str) -> Value {
+            Value::Tuple(vec![Value::str(artist), Value::str(name)])
+        }
+
+        async fn query_composite(sut: &mut Sut, entity: u64) -> Vec<Vec<Value>> {
+            // [:find ?tuple :where [entity :album/artist+name ?tuple]]
+            sut.query(
+                Query::new().find(Find::variable("?tuple")).r#where(
+                    Clause::new()
+                        .with_entity(Pattern::entity(entity))
+                        .with_attribute(Pattern::ident("album/artist+name"))
+                        .with_value(Pattern::variable("?tuple")),
+                ),
+            )
+            .await
+        }
+
+        #[tokio::test]
+        async fn transact_tuples() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_album_schema()).await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("album")
+                            .assert("album/ratings", Value::Tuple(vec![5.into(), 4.into()]))
+                            .assert(
+                                "album/chart",
+                                Value::Tuple(vec![Value::str("UK"), Value::Nil]),
+                            ),
+                    ),
+                )
+                .await;
+            let album = tx_result.temp_ids["album"];
+
+            // [:find ?album :where [?album :album/chart ["UK" nil]]]
+            let query_result = sut
+                .query(
+                    Query::new().find(Find::variable("?album")).r#where(
+                        Clause::new()
+                            .with_entity(Pattern::variable("?album"))
+                            .with_attribute(Pattern::ident("album/chart"))
+                            .with_value(Pattern::value(Value::Tuple(vec![
+                                Value::str("UK"),
+                                Value::Nil,
+                            ]))),
+                    ),
+                )
+                .await;
+
+            assert_that!(
+                query_result,
+                unordered_elements_are![elements_are![eq(Value::Ref(album))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_tuples_with_invalid_elements() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_album_schema()).await;
+
+            let invalid = [
+                (
+                    "album/ratings",
+                    Value::Tuple(vec![5.into(), Value::str("good")]),
+                ),
+                ("album/chart", Value::Tuple(vec![Value::str("UK")])),
+                (
+                    "album/chart",
+                    Value::Tuple(vec![1.into(), Value::str("UK")]),
+                ),
+            ];
+            for (attribute, value) in invalid {
+                let result = sut
+                    .try_transact(
+                        Transaction::new().with(EntityOperation::on_new().assert(attribute, value)),
+                    )
+                    .await;
+                assert!(result.is_none());
+            }
+        }
+
+        #[tokio::test]
+        async fn maintain_composite_tuples() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_album_schema()).await;
+
+            let tx_result = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("album")
+                            .assert("album/artist", "The Beatles")
+                            .assert("album/name", "Let It Be"),
+                    ),
+                )
+                .await;
+            let album = tx_result.temp_ids["album"];
+            assert_that!(
+                query_composite(&mut sut, album).await,
+                unordered_elements_are![elements_are![eq(artist_and_name(
+                    "The Beatles",
+                    "Let It Be"
+                ))]]
+            );
+
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_id(album).assert("album/name", "Abbey Road")),
+            )
+            .await;
+            assert_that!(
+                query_composite(&mut sut, album).await,
+                unordered_elements_are![elements_are![eq(artist_and_name(
+                    "The Beatles",
+                    "Abbey Road"
+                ))]]
+            );
+
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_id(album).retract("album/artist", "The Beatles")),
+            )
+            .await;
+            assert_that!(
+                query_composite(&mut sut, album).await,
+                unordered_elements_are![elements_are![eq(Value::Tuple(vec![
+                    Value::Nil,
+                    Value::str("Abbey Road")
+                ]))]]
+            );
+
+            sut.transact(Transaction::new().retract_entity(album)).await;
+            assert_that!(query_composite(&mut sut, album).await, empty());
+        }
+
+        #[tokio::test]
+        async fn reject_duplicate_composite_values() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_album_schema()).await;
+            sut.transact(
+                Transaction::new().with(
+                    EntityOperation::on_new()
+                        .assert("album/artist", "The Beatles")
+                        .assert("album/name", "Let It Be"),
+                ),
+            )
+            .await;
+
+            // The same name by another artist is fine.
+            let other_artist = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("album/artist", "Aretha Franklin")
+                            .assert("album/name", "Let It Be"),
+                    ),
+                )
+                .await;
+            assert!(other_artist.is_some());
+
+            let result = sut
+                .conn
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("album/artist", "The Beatles")
+                            .assert("album/name", "Let It Be"),
+                    ),
+                )
+                .await;
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::DuplicateUniqueValue { .. }
+                ))
+            ));
+        }
+
+        #[tokio::test]
+        async fn reject_asserting_composite_tuples() {
+            let mut sut = Sut::new().await;
+            sut.transact(create_album_schema()).await;
+
+            let result = sut
+                .conn
+                .transact(Transaction::new().with(EntityOperation::on_new().assert(
+                    "album/artist+name",
+                    artist_and_name("The Beatles", "Let It Be"),
+                )))
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::CompositeTupleTransacted(_)
+                ))
+            ));
+        }
+    }
+
+    mod history {
+        use super::*;
+        use crate::storage::attribute_resolver::ResolveError;
+
+        fn values_query(attribute: &str) -> Query {
+            // [:find ?value
+            //  :where [?entity attribute ?value]]
+            Query::new().find(Find::variable("?value")).r#where(
+                Clause::new()
+                    .with_entity(Pattern::variable("?entity"))
+                    .with_attribute(Pattern::ident(attribute))
+                    .with_value(Pattern::variable("?value")),
+            )
+        }
+
+        async fn set_value(sut: &mut Sut, entity: u64, attribute: &str, value: Value) -> u64 {
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_id(entity).assert(attribute, value)),
+                )
+                .await;
+            report.db_after.basis_tx()
+        }
+
+        #[tokio::test]
+        async fn drop_superseded_values_of_attributes_without_history() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new()
+                    .with(AttributeDefinition::new("page/views", ValueType::U64).no_history()),
+            )
+            .await;
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("page").assert("page/views", 1u64)),
+                )
+                .await;
+            let page_id = report.temp_ids["page"];
+            let first_tx = report.db_after.basis_tx();
+
+            set_value(&mut sut, page_id, "page/views", Value::U64(2)).await;
+
+            assert_that!(
+                sut.query(values_query("page/views")).await,
+                elements_are![elements_are![eq(Value::U64(2))]]
+            );
+            assert_that!(
+                sut.query_at_snapshot(first_tx, values_query("page/views"))
+                    .await,
+                empty()
+            );
+        }
+
+        #[tokio::test]
+        async fn drop_superseded_values_once_history_is_disabled() {
+            let mut sut = Sut::new().await;
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("john").assert("person/born", 1940)),
+                )
+                .await;
+            let john_id = report.temp_ids["john"];
+            let first_tx = report.db_after.basis_tx();
+
+            sut.transact(
+                Transaction::new().with(AttributeAlteration::new("person/born").no_history()),
+            )
+            .await;
+            set_value(&mut sut, john_id, "person/born", Value::I64(1941)).await;
+
+            assert_that!(
+                sut.query_at_snapshot(first_tx, values_query("person/born"))
+                    .await,
+                empty()
+            );
+            assert_that!(
+                sut.query(values_query("person/born")).await,
+                elements_are![elements_are![eq(Value::I64(1941))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn purge_history_before_tx() {
+            let mut sut = Sut::new().await;
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("john").assert("artist/name", "John")),
+                )
+                .await;
+            let john_id = report.temp_ids["john"];
+            let first_tx = report.db_after.basis_tx();
+            let second_tx = set_value(&mut sut, john_id, "artist/name", Value::str("Johnny")).await;
+            let third_tx =
+                set_value(&mut sut, john_id, "artist/name", Value::str("John Lennon")).await;
+
+            let purged = sut
+                .conn
+                .purge_history(&["artist/name"], third_tx)
+                .await
+                .expect("Unable to purge history");
+
+            // "John" was asserted and retracted before the third transaction
+            assert_eq!(2, purged);
+            assert_that!(
+                sut.query_at_snapshot(first_tx, values_query("artist/name"))
+                    .await,
+                empty()
+            );
+            assert_that!(
+                sut.query_at_snapshot(second_tx, values_query("artist/name"))
+                    .await,
+                elements_are![elements_are![eq(Value::str("Johnny"))]]
+            );
+            assert_that!(
+                sut.query(values_query("artist/name")).await,
+                elements_are![elements_are![eq(Value::str("John Lennon"))]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_purging_history_of_missing_attribute() {
+            let sut = Sut::new().await;
+
+            let result = sut.conn.purge_history(&["foo/bar"], u64::MAX).await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::ResolveError(ResolveError::IdentNotFound(
+                    _
+                )))
+            ));
+        }
+    }
+
+    mod excision {
+        use super::*;
+        use crate::schema::DB_EXCISE_ATTRS_IDENT;
+        use crate::schema::DB_EXCISE_IDENT;
+        use crate::schema::DB_IDENT_IDENT;
+        use crate::storage::attribute_resolver::ResolveError;
+
+        fn attribute_query(attribute: &str) -> Query {
+            // [:find ?entity ?value
+            //  :where [?entity attribute ?value]]
+            Query::new()
+                .find(Find::variable("?entity"))
+                .find(Find::variable("?value"))
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?entity"))
+                        .with_attribute(Pattern::ident(attribute))
+                        .with_value(Pattern::variable("?value")),
+                )
+        }
+
+        async fn create_people(sut: &mut Sut) -> (u64, u64, u64) {
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(
+                            EntityOperation::on_temp_id("john")
+                                .assert("person/name", "John")
+                                .assert("person/email", "john@beatles.com"),
+                        )
+                        .with(
+                            EntityOperation::on_temp_id("paul")
+                                .assert("person/name", "Paul")
+                                .assert("person/email", "paul@beatles.com"),
+                        ),
+                )
+                .await;
+            (
+                report.temp_ids["john"],
+                report.temp_ids["paul"],
+                report.db_after.basis_tx(),
+            )
+        }
+
+        #[tokio::test]
+        async fn excise_entity() {
+            let mut sut = Sut::new().await;
+            let (john_id, paul_id, created_tx) = create_people(&mut sut).await;
+            sut.transact(
+                Transaction::new()
+                    .with(EntityOperation::on_id(john_id).assert("person/name", "John Lennon")),
+            )
+            .await;
+
+            sut.transact(Transaction::new().excise(Excision::new(john_id)))
+                .await;
+
+            assert_that!(
+                sut.query(attribute_query("person/name")).await,
+                elements_are![elements_are![
+                    eq(Value::Ref(paul_id)),
+                    eq(Value::str("Paul"))
+                ]]
+            );
+            // Earlier points in time don't resurface the excised datoms either
+            assert_that!(
+                sut.query_at_snapshot(created_tx, attribute_query("person/name"))
+                    .await,
+                elements_are![elements_are![
+                    eq(Value::Ref(paul_id)),
+                    eq(Value::str("Paul"))
+                ]]
+            );
+            // Lookups by value use AVET for unique attributes
+            let by_email = sut
+                .query_at_snapshot(
+                    created_tx,
+                    Query::new().find(Find::variable("?person")).r#where(
+                        Clause::new()
+                            .with_entity(Pattern::variable("?person"))
+                            .with_attribute(Pattern::ident("person/email"))
+                            .with_value(Pattern::value("john@beatles.com")),
+                    ),
+                )
+                .await;
+            assert!(by_email.is_empty());
+        }
+
+        #[tokio::test]
+        async fn excise_values_of_attributes() {
+            let mut sut = Sut::new().await;
+            let (john_id, paul_id, _) = create_people(&mut sut).await;
+
+            sut.transact(
+                Transaction::new().excise(Excision::new(john_id).attribute("person/email")),
+            )
+            .await;
+
+            assert_that!(
+                sut.query(attribute_query("person/email")).await,
+                elements_are![elements_are![
+                    eq(Value::Ref(paul_id)),
+                    eq(Value::str("paul@beatles.com"))
+                ]]
+            );
+            assert_that!(
+                sut.query(attribute_query("person/name")).await,
+                unordered_elements_are![
+                    elements_are![eq(Value::Ref(john_id)), eq(Value::str("John"))],
+                    elements_are![eq(Value::Ref(paul_id)), eq(Value::str("Paul"))],
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn record_excisions() {
+            let mut sut = Sut::new().await;
+            let (john_id, _, _) = create_people(&mut sut).await;
+
+            let report = sut
+                .transact(
+                    Transaction::new().excise(Excision::new(john_id).attribute("person/email")),
+                )
+                .await;
+
+            // [:find ?tx ?attribute
+            //  :where [?excision :db/excise john ?tx]
+            //         [?excision :db/excise/attrs ?attribute]]
+            let query = Query::new()
+                .find(Find::variable("?tx"))
+                .find(Find::variable("?attribute"))
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?excision"))
+                        .with_attribute(Pattern::ident(DB_EXCISE_IDENT))
+                        .with_value(Pattern::value(Value::Ref(john_id)))
+                        .with_tx(Pattern::variable("?tx")),
+                )
+                .r#where(
+                    Clause::new()
+                        .with_entity(Pattern::variable("?excision"))
+                        .with_attribute(Pattern::ident(DB_EXCISE_ATTRS_IDENT))
+                        .with_value(Pattern::variable("?attribute")),
+                );
+            let email = sut
+                .conn
+                .resolver()
+                .resolve(&*sut.conn.storage().await, "person/email", u64::MAX)
+                .await
+                .expect("Unable to resolve attribute");
+            assert_that!(
+                sut.query(query).await,
+                elements_are![elements_are![
+                    eq(Value::Ref(report.db_after.basis_tx())),
+                    eq(Value::Ref(email.id))
+                ]]
+            );
+        }
+
+        #[tokio::test]
+        async fn reject_excising_attributes() {
+            let sut = Sut::new().await;
+            let email = sut
+                .conn
+                .resolver()
+                .resolve(&*sut.conn.storage().await, "person/email", u64::MAX)
+                .await
+                .expect("Unable to resolve attribute");
+
+            let result = sut
+                .conn
+                .transact(Transaction::new().excise(Excision::new(email.id)))
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::ExcisedAttribute(_)
+                ))
+            ));
+        }
+
+        #[tokio::test]
+        async fn forget_ident_of_excised_entity() {
+            let mut sut = Sut::new().await;
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(AttributeDefinition::new("order/status", ValueType::Ref))
+                        .with(
+                            EntityOperation::on_temp_id("active")
+                                .assert(DB_IDENT_IDENT, Value::keyword("status/active")),
+                        ),
+                )
+                .await;
+            let active_id = report.temp_ids["active"];
+            // Resolve and cache the ident.
+            sut.transact(Transaction::new().with(
+                EntityOperation::on_new().assert("order/status", Value::keyword("status/active")),
+            ))
+            .await;
+
+            sut.transact(Transaction::new().excise(Excision::new(active_id)))
+                .await;
+
+            let result = sut
+                .conn
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("order/status", Value::keyword("status/active")),
+                    ),
+                )
+                .await;
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::ResolveError(ResolveError::IdentNotFound(_))
+                ))
+            ));
+        }
+    }
+
+    mod predicates {
+        use super::*;
+        use crate::tx::predicate::*;
+
+        fn create_config() -> TransactorConfig {
+            TransactorConfig::new()
+                .with_predicate("person/short", MaxLength(8))
+                .with_predicate("person/adult", Range::new(18i64, 150i64))
+                .with_predicate(
+                    "person/website",
+                    Matches::new("^https?://").expect("Invalid pattern"),
+                )
+        }
+
+        fn create_predicate_schema() -> Transaction {
+            Transaction::new()
+                .with(
+                    AttributeDefinition::new("person/nickname", ValueType::Str)
+                        .with_predicate("person/short"),
+                )
+                .with(
+                    AttributeDefinition::new("person/age", ValueType::I64)
+                        .with_predicate("person/adult"),
+                )
+                .with(
+                    AttributeDefinition::new("person/website", ValueType::Str)
+                        .with_predicate("person/website"),
+                )
+        }
+
+        fn is_predicate_failed(
+            result: &Result<
+                TransactionReport,
+                ConnectionError<StorageError<'static>, std::convert::Infallible>,
+            >,
+            expected: &str,
+        ) -> bool {
+            matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::PredicateFailed { predicate, .. }
+                )) if predicate == expected
+            )
+        }
+
+        #[tokio::test]
+        async fn accept_values_satisfying_predicates() {
+            let sut = Sut::with_schema(create_config(), create_predicate_schema()).await;
+
+            let result = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("person/nickname", "Johnny")
+                            .assert("person/age", 40)
+                            .assert("person/website", "https://www.johnlennon.com"),
+                    ),
+                )
+                .await;
+
+            assert!(result.is_some());
+        }
+
+        #[tokio::test]
+        async fn reject_values_failing_predicates() {
+            let sut = Sut::with_schema(create_config(), create_predicate_schema()).await;
+
+            let nickname = sut
+                .conn
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/nickname", "Johnny Lennon")),
+                )
+                .await;
+            let age = sut
+                .conn
+                .transact(
+                    Transaction::new().with(EntityOperation::on_new().assert("person/age", 12)),
+                )
+                .await;
+            let website = sut
+                .conn
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/website", "johnlennon.com")),
+                )
+                .await;
+
+            assert!(is_predicate_failed(&nickname, "person/short"));
+            assert!(is_predicate_failed(&age, "person/adult"));
+            assert!(is_predicate_failed(&website, "person/website"));
+        }
+
+        #[tokio::test]
+        async fn reject_values_of_attributes_with_missing_predicates() {
+            let mut sut = Sut::new().await;
+            sut.transact(
+                Transaction::new().with(
+                    AttributeDefinition::new("person/nickname", ValueType::Str)
+                        .with_predicate("person/short"),
+                ),
+            )
+            .await;
+
+            let result = sut
+                .conn
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/nickname", "Johnny")),
+                )
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::PredicateNotFound(_)
+                ))
+            ));
+        }
+
+        #[tokio::test]
+        async fn alter_predicates_of_attribute() {
+            let mut sut = Sut::with_schema(create_config(), create_predicate_schema()).await;
+
+            sut.transact(
+                Transaction::new().with(
+                    AttributeAlteration::new("person/nickname")
+                        .without_predicate("person/short")
+                        .with_predicate("person/website"),
+                ),
+            )
+            .await;
+
+            let long = sut
+                .try_transact(Transaction::new().with(
+                    EntityOperation::on_new().assert("person/nickname", "https://johnny.com"),
+                ))
+                .await;
+            let short = sut
+                .conn
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/nickname", "Johnny")),
+                )
+                .await;
+            assert!(long.is_some());
+            assert!(is_predicate_failed(&short, "person/website"));
+        }
+    }
+
+    mod entity_specs {
+        use super::*;
+        use crate::schema::spec::EntitySpec;
+        use crate::schema::DB_ENSURE_ID;
+
+        fn create_spec_schema() -> Transaction {
+            Transaction::new().with(
+                EntitySpec::new("spec/person")
+                    .require("person/name")
+                    .require("person/born"),
+            )
+        }
+
+        #[tokio::test]
+        async fn accept_entities_with_required_attributes() {
+            let mut sut = Sut::with_schema(TransactorConfig::default(), create_spec_schema()).await;
+
+            let report = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("person/name", "John")
+                            .assert("person/born", 1940)
+                            .ensure("spec/person"),
+                    ),
+                )
+                .await;
+
+            // Ensuring a spec doesn't save anything.
+            assert!(!report
+                .tx_data
+                .iter()
+                .any(|datom| datom.attribute == DB_ENSURE_ID));
+        }
+
+        #[tokio::test]
+        async fn validate_existing_attributes() {
+            let mut sut = Sut::with_schema(TransactorConfig::default(), create_spec_schema()).await;
+            let report = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("john")
+                            .assert("person/name", "John")
+                            .assert("person/born", 1940),
+                    ),
+                )
+                .await;
+            let john_id = report.temp_ids["john"];
+
+            let result = sut
+                .try_transact(
+                    Transaction::new().with(EntityOperation::on_id(john_id).ensure("spec/person")),
+                )
+                .await;
+
+            assert!(result.is_some());
+        }
+
+        #[tokio::test]
+        async fn reject_entities_missing_required_attributes() {
+            let sut = Sut::with_schema(TransactorConfig::default(), create_spec_schema()).await;
+
+            let result = sut
+                .conn
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("person/name", "John")
+                            .ensure("spec/person"),
+                    ),
+                )
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(
+                    TransactionError::SpecNotSatisfied { spec, attribute, .. }
+                )) if spec == "spec/person" && attribute == "person/born"
+            ));
+        }
+
+        #[tokio::test]
+        async fn reject_entities_whose_required_attributes_are_retracted() {
+            let mut sut = Sut::with_schema(TransactorConfig::default(), create_spec_schema()).await;
+            let report = sut
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_temp_id("john")
+                            .assert("person/name", "John")
+                            .assert("person/born", 1940),
+                    ),
+                )
+                .await;
+            let john_id = report.temp_ids["john"];
+
+            let result = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_id(john_id)
+                            .retract("person/born", 1940)
+                            .ensure("spec/person"),
+                    ),
+                )
+                .await;
+
+            assert!(result.is_none());
+        }
+    }
+
+    mod enums {
+        use super::*;
+        use crate::schema::DB_IDENT_IDENT;
+        use crate::storage::attribute_resolver::ResolveError;
+
+        fn create_enum_schema() -> Transaction {
+            Transaction::new()
+                .with(AttributeDefinition::new("order/status", ValueType::Ref))
+                .with(
+                    EntityOperation::on_new()
+                        .assert(DB_IDENT_IDENT, Value::keyword("status/active")),
+                )
+                .with(
+                    EntityOperation::on_new()
+                        .assert(DB_IDENT_IDENT, Value::keyword("status/cancelled")),
+                )
+        }
+
+        #[tokio::test]
+        async fn reference_enum_by_ident() {
+            let mut sut = Sut::with_schema(TransactorConfig::default(), create_enum_schema()).await;
+
+            sut.transact(Transaction::new().with(
+                EntityOperation::on_new().assert("order/status", Value::keyword("status/active")),
+            ))
+            .await;
+
+            let query_result = sut
+                .query(
+                    Query::new()
+                        .find(Find::variable("?ident"))
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?status"))
+                                .with_attribute(Pattern::ident(DB_IDENT_IDENT))
+                                .with_value(Pattern::variable("?ident")),
+                        )
+                        .r#where(
+                            Clause::new()
+                                .with_entity(Pattern::variable("?order"))
+                                .with_attribute(Pattern::ident("order/status"))
+                                .with_value(Pattern::variable("?status")),
+                        ),
+                )
+                .await;
+
+            assert_eq!(query_result, vec![vec![Value::keyword("status/active")]]);
+        }
+
+        #[tokio::test]
+        async fn query_by_enum_ident() {
+            let mut sut = Sut::with_schema(TransactorConfig::default(), create_enum_schema()).await;
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(
+                            EntityOperation::on_temp_id("order1")
+                                .assert("order/status", Value::keyword("status/active")),
+                        )
+                        .with(
+                            EntityOperation::on_temp_id("order2")
+                                .assert("order/status", Value::keyword("status/cancelled")),
+                        ),
+                )
+                .await;
+
+            let query_result = sut
+                .query(
+                    Query::new().find(Find::variable("?order")).r#where(
+                        Clause::new()
+                            .with_entity(Pattern::variable("?order"))
+                            .with_attribute(Pattern::ident("order/status"))
+                            .with_value(Pattern::value(Value::keyword("status/cancelled"))),
+                    ),
+                )
+                .await;
+
+            let order2 = report.temp_ids["order2"];
+            assert_eq!(query_result, vec![vec![Value::Ref(order2)]]);
+        }
+
+        #[tokio::test]
+        async fn reject_unknown_enum_ident() {
+            let sut = Sut::with_schema(TransactorConfig::default(), create_enum_schema()).await;
+
+            let result = sut
+                .conn
+                .transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("order/status", Value::keyword("status/unknown")),
+                    ),
+                )
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(ConnectionError::TransactionError(TransactionError::ResolveError(
+                    ResolveError::IdentNotFound(ident)
+                ))) if ident == "status/unknown"
+            ));
+        }
+
+        #[tokio::test]
+        async fn keyword_values_of_other_attributes_are_not_resolved() {
+            let mut sut = Sut::with_schema(TransactorConfig::default(), create_enum_schema()).await;
+            sut.transact(
+                Transaction::new()
+                    .with(AttributeDefinition::new("order/label", ValueType::Keyword)),
+            )
+            .await;
+
+            let result = sut
+                .try_transact(
+                    Transaction::new().with(
+                        EntityOperation::on_new()
+                            .assert("order/label", Value::keyword("status/unknown")),
+                    ),
+                )
+                .await;
+
+            assert!(result.is_some());
+        }
+    }
+
+    mod partitions {
+        use super::*;
+        use crate::schema::partition::Partition;
+
+        #[tokio::test]
+        async fn create_entities_in_partitions() {
+            let mut sut = Sut::new().await;
+            let schema = sut
+                .transact(
+                    Transaction::new().with(AttributeDefinition::new("order/id", ValueType::U64)),
+                )
+                .await;
+
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("john").assert("person/name", "John"))
+                        .with(
+                            EntityOperation::on_temp_id("order")
+                                .in_partition(Partition::custom(0))
+                                .assert("order/id", 1u64),
+                        ),
+                )
+                .await;
+
+            let attribute = schema
+                .tx_data
+                .iter()
+                .find(|datom| datom.value == Value::str("order/id"))
+                .map(|datom| datom.entity);
+            assert_eq!(Some(Partition::DB), attribute.map(Partition::of));
+            assert_eq!(Partition::TX, Partition::of(report.db_after.basis_tx()));
+            assert_eq!(Partition::USER, Partition::of(report.temp_ids["john"]));
+            assert_eq!(
+                Partition::custom(0),
+                Partition::of(report.temp_ids["order"])
+            );
+        }
+
+        #[tokio::test]
+        async fn assign_increasing_ids_across_partitions() {
+            let mut sut = Sut::new().await;
+
+            let report1 = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("john").assert("person/name", "John")),
+                )
+                .await;
+            let report2 = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_temp_id("paul").assert("person/name", "Paul")),
+                )
+                .await;
+
+            assert!(report1.db_after.basis_tx() < report2.db_after.basis_tx());
+            assert!(report1.temp_ids["john"] < report2.temp_ids["paul"]);
+        }
+
+        #[tokio::test]
+        async fn query_at_partitioned_tx() {
+            let mut sut = Sut::new().await;
+            let report = sut
+                .transact(
+                    Transaction::new()
+                        .with(EntityOperation::on_new().assert("person/name", "John")),
+                )
+                .await;
+            sut.transact(
+                Transaction::new().with(EntityOperation::on_new().assert("person/name", "Paul")),
+            )
+            .await;
+
+            let query = Query::new().find(Find::variable("?name")).r#where(
+                Clause::new()
+                    .with_entity(Pattern::variable("?person"))
+                    .with_attribute(Pattern::ident("person/name"))
+                    .with_value(Pattern::variable("?name")),
+            );
+            let query_result = sut
+                .query_at_snapshot(report.db_after.basis_tx(), query)
+                .await;
+
+            assert_eq!(query_result, vec![vec![Value::str("John")]]);
+        }
+    }
 }
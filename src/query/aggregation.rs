@@ -134,7 +134,7 @@ pub enum AggregationFunction {
 }
 
 impl AggregationFunction {
-    pub fn empty_state(&self) -> AggregationState {
+    pub fn empty_state(&self) -> AggregationState<'_> {
         match self {
             AggregationFunction::Count => AggregationState::count(),
             AggregationFunction::Min(variable) => AggregationState::min(variable),
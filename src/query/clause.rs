@@ -3,7 +3,7 @@ use crate::query::pattern::*;
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Clause {
-    pub entity: Pattern<u64>,
+    pub entity: Pattern<EntityIdentifier>,
     pub attribute: Pattern<AttributeIdentifier>,
     pub value: Pattern<Value>,
     pub tx: Pattern<u64>,
@@ -14,7 +14,7 @@ impl Clause {
         Self::default()
     }
 
-    pub fn with_entity(mut self, entity: Pattern<u64>) -> Self {
+    pub fn with_entity(mut self, entity: Pattern<EntityIdentifier>) -> Self {
         self.entity = entity;
         self
     }
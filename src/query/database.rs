@@ -1,12 +1,18 @@
+use crate::datom::Value;
 use crate::query::pattern::AttributeIdentifier;
+use crate::query::pattern::EntityIdentifier;
 use crate::query::pattern::Pattern;
 use crate::query::projector::Projector;
 use crate::query::resolver::Resolver;
 use crate::query::*;
+use crate::schema::attribute::ValueType;
+use crate::schema::partition::{counter, Partition};
 use crate::storage::attribute_resolver::*;
+use crate::storage::lookup_ref::resolve_lookup_ref;
 use crate::storage::*;
 use either::*;
 
+#[derive(Clone, Copy, Debug)]
 pub struct Database {
     basis_tx: u64,
 }
@@ -16,6 +22,16 @@ impl Database {
         Self { basis_tx }
     }
 
+    /// Returns the database value which includes all transactions, given the latest entity ID
+    /// stored (see `ReadStorage::latest_entity_id`).
+    pub fn latest(latest_entity_id: u64) -> Self {
+        Self::new(Partition::TX.entity(counter(latest_entity_id)))
+    }
+
+    pub fn basis_tx(&self) -> u64 {
+        self.basis_tx
+    }
+
     pub async fn query<'a, S: ReadStorage<'a>>(
         &self,
         storage: &'a S,
@@ -37,8 +53,11 @@ impl Database {
         }
     }
 
-    /// Resolves attribute idents. Mutates input `query` such that clauses with
-    /// `AttributeIdentifier::Ident` will be replaced with `AttributeIdentifier::Id`.
+    /// Resolves attribute idents, entity lookup refs and entity idents. Mutates input `query` such
+    /// that clauses with `AttributeIdentifier::Ident` will be replaced with `AttributeIdentifier::Id`,
+    /// clauses with `EntityIdentifier::LookupRef` will be replaced with `EntityIdentifier::Id`, and
+    /// keyword values of reference attributes will be replaced with the entity having that
+    /// `db/ident`.
     async fn resolve_idents<'a, S: ReadStorage<'a>>(
         &self,
         storage: &'a S,
@@ -50,6 +69,26 @@ impl Database {
                 let attribute = resolver.resolve(storage, ident, self.basis_tx).await?;
                 clause.attribute = Pattern::id(attribute.id);
             }
+            if let Pattern::Constant(EntityIdentifier::LookupRef(lookup_ref)) = &clause.entity {
+                let entity =
+                    resolve_lookup_ref(storage, resolver, lookup_ref, self.basis_tx).await?;
+                clause.entity = Pattern::entity(entity);
+            }
+            if let (
+                Pattern::Constant(AttributeIdentifier::Id(attribute_id)),
+                Pattern::Constant(Value::Keyword(ident)),
+            ) = (&clause.attribute, &clause.value)
+            {
+                let attribute = resolver
+                    .resolve_id(storage, *attribute_id, self.basis_tx)
+                    .await?;
+                if attribute.definition.value_type == ValueType::Ref {
+                    let entity = resolver
+                        .resolve_entity(storage, ident, self.basis_tx)
+                        .await?;
+                    clause.value = Pattern::Constant(Value::Ref(entity));
+                }
+            }
         }
         Ok(())
     }
@@ -11,6 +11,7 @@ use crate::datom::Value;
 use crate::query::aggregation::*;
 use crate::query::clause::*;
 use crate::storage::attribute_resolver::ResolveError;
+use crate::storage::lookup_ref::LookupRefError;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
@@ -118,6 +119,8 @@ pub enum QueryError<S> {
     StorageError(#[from] S),
     #[error("resolve error")]
     ResolveError(#[from] ResolveError<S>),
+    #[error("lookup ref error")]
+    LookupRefError(#[from] LookupRefError<S>),
     #[error("invalid variable {0} for find clause")]
     InvalidFindVariable(String),
 }
@@ -14,6 +14,18 @@ impl<T> Pattern<T> {
     }
 }
 
+impl Pattern<EntityIdentifier> {
+    pub fn entity(id: u64) -> Self {
+        Self::Constant(EntityIdentifier::Id(id))
+    }
+
+    pub fn lookup_ref(attribute: &str, value: impl Into<Value>) -> Self {
+        Self::Constant(EntityIdentifier::LookupRef(LookupRef::new(
+            attribute, value,
+        )))
+    }
+}
+
 impl Pattern<AttributeIdentifier> {
     pub fn id(id: u64) -> Self {
         Self::Constant(AttributeIdentifier::Id(id))
@@ -30,8 +42,48 @@ impl Pattern<Value> {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntityIdentifier {
+    Id(u64),
+    LookupRef(LookupRef),
+}
+
+/// Identifies an entity by a value of a unique attribute, e.g. `[:artist/name "John Lenon"]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LookupRef {
+    pub attribute: String,
+    pub value: Value,
+}
+
+impl LookupRef {
+    pub fn new(attribute: &str, value: impl Into<Value>) -> Self {
+        Self {
+            attribute: attribute.to_string(),
+            value: value.into(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum AttributeIdentifier {
     Ident(String),
     Id(u64),
 }
+
+impl From<&str> for AttributeIdentifier {
+    fn from(ident: &str) -> Self {
+        Self::Ident(ident.to_string())
+    }
+}
+
+impl From<String> for AttributeIdentifier {
+    fn from(ident: String) -> Self {
+        Self::Ident(ident)
+    }
+}
+
+impl From<u64> for AttributeIdentifier {
+    fn from(id: u64) -> Self {
+        Self::Id(id)
+    }
+}
@@ -46,8 +46,9 @@ impl<'a, S: ReadStorage<'a>> Resolver<'a, S> {
             // Assignment doesn't satisfy the predicates, reject
             return self.next();
         }
-        if assignment.is_complete() {
-            // Assignment is complete - emit
+        let is_last_clause = self.frame.clause_index + 1 == self.clauses.len();
+        if assignment.is_complete() && is_last_clause {
+            // Assignment is complete and satisfies all clauses - emit
             return Some(Ok(assignment.complete()));
         }
         // Push a new frame to the stack and continue
@@ -102,3 +103,44 @@ impl Frame {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datom::Value;
+    use crate::query::clause::Clause;
+    use crate::query::pattern::Pattern;
+    use crate::storage::memory::InMemoryStorage;
+
+    #[test]
+    fn later_clauses_filter_complete_assignments() {
+        let name = 100;
+        let born = 101;
+        let mut storage = InMemoryStorage::new();
+        let datoms = [
+            Datom::add(1, name, "John", 1000),
+            Datom::add(1, born, 1940, 1000),
+            Datom::add(2, name, "Paul", 1000),
+            Datom::add(2, born, 1942, 1000),
+        ];
+        storage.save(&datoms).expect("Unable to save datoms");
+
+        // [?person name ?name] completes the assignment, [?person born 1940] must still apply.
+        let clauses = vec![
+            Clause::new()
+                .with_entity(Pattern::variable("?person"))
+                .with_attribute(Pattern::id(name))
+                .with_value(Pattern::variable("?name")),
+            Clause::new()
+                .with_entity(Pattern::variable("?person"))
+                .with_attribute(Pattern::id(born))
+                .with_value(Pattern::value(1940)),
+        ];
+        let resolver = Resolver::new(&storage, clauses, Vec::new(), u64::MAX);
+        let names: Vec<_> = resolver
+            .map(|assignment| assignment.expect("Unable to resolve")["?name"].clone())
+            .collect();
+
+        assert_eq!(vec![Value::str("John")], names);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::datom::Value;
+use crate::schema::partition::Partition;
 use crate::schema::*;
 use crate::tx;
 
@@ -10,6 +11,14 @@ pub enum ValueType {
     Decimal = 3,
     Str = 4,
     Ref = 5,
+    Bool = 6,
+    Keyword = 7,
+    Instant = 8,
+    Uuid = 9,
+    Double = 10,
+    Bytes = 11,
+    BigInt = 12,
+    Tuple = 13,
 }
 
 impl TryFrom<u64> for ValueType {
@@ -24,6 +33,14 @@ impl TryFrom<u64> for ValueType {
     ///     ValueType::Decimal,
     ///     ValueType::Str,
     ///     ValueType::Ref,
+    ///     ValueType::Bool,
+    ///     ValueType::Keyword,
+    ///     ValueType::Instant,
+    ///     ValueType::Uuid,
+    ///     ValueType::Double,
+    ///     ValueType::Bytes,
+    ///     ValueType::BigInt,
+    ///     ValueType::Tuple,
     /// ];
     /// for value_type in value_types {
     ///     assert_eq!(Ok(value_type), ValueType::try_from(value_type as u64));
@@ -37,6 +54,14 @@ impl TryFrom<u64> for ValueType {
             3 => Ok(Self::Decimal),
             4 => Ok(Self::Str),
             5 => Ok(Self::Ref),
+            6 => Ok(Self::Bool),
+            7 => Ok(Self::Keyword),
+            8 => Ok(Self::Instant),
+            9 => Ok(Self::Uuid),
+            10 => Ok(Self::Double),
+            11 => Ok(Self::Bytes),
+            12 => Ok(Self::BigInt),
+            13 => Ok(Self::Tuple),
             x => Err(InvalidTag(x)),
         }
     }
@@ -54,6 +79,14 @@ impl From<&Value> for ValueType {
     /// assert_eq!(ValueType::from(&Value::Decimal(42.into())), ValueType::Decimal);
     /// assert_eq!(ValueType::from(&Value::str("foo")), ValueType::Str);
     /// assert_eq!(ValueType::from(&Value::Ref(42)), ValueType::Ref);
+    /// assert_eq!(ValueType::from(&Value::Bool(true)), ValueType::Bool);
+    /// assert_eq!(ValueType::from(&Value::keyword("foo/bar")), ValueType::Keyword);
+    /// assert_eq!(ValueType::from(&Value::Instant(42)), ValueType::Instant);
+    /// assert_eq!(ValueType::from(&Value::Uuid(uuid::Uuid::nil())), ValueType::Uuid);
+    /// assert_eq!(ValueType::from(&Value::from(4.2)), ValueType::Double);
+    /// assert_eq!(ValueType::from(&Value::Bytes(vec![42])), ValueType::Bytes);
+    /// assert_eq!(ValueType::from(&Value::BigInt(42.into())), ValueType::BigInt);
+    /// assert_eq!(ValueType::from(&Value::Tuple(vec![])), ValueType::Tuple);
     /// assert_ne!(ValueType::from(&Value::U64(42)), ValueType::Str);
     /// ```
     fn from(value: &Value) -> Self {
@@ -64,6 +97,14 @@ impl From<&Value> for ValueType {
             Value::Decimal(_) => Self::Decimal,
             Value::Str(_) => Self::Str,
             Value::Ref(_) => Self::Ref,
+            Value::Bool(_) => Self::Bool,
+            Value::Keyword(_) => Self::Keyword,
+            Value::Instant(_) => Self::Instant,
+            Value::Uuid(_) => Self::Uuid,
+            Value::Double(_) => Self::Double,
+            Value::Bytes(_) => Self::Bytes,
+            Value::BigInt(_) => Self::BigInt,
+            Value::Tuple(_) => Self::Tuple,
         }
     }
 }
@@ -93,6 +134,39 @@ impl TryFrom<u64> for Cardinality {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Unique {
+    Value = 1,    // Only one entity can have a given value, asserting it again is rejected.
+    Identity = 2, // Only one entity can have a given value, asserting it again upserts the entity.
+}
+
+impl TryFrom<u64> for Unique {
+    type Error = InvalidTag;
+
+    /// ```
+    /// use rustomic::schema::attribute::*;
+    ///
+    /// assert_eq!(Ok(Unique::Value), Unique::try_from(1));
+    /// assert_eq!(Ok(Unique::Identity), Unique::try_from(2));
+    /// assert_eq!(Err(InvalidTag(42)), Unique::try_from(42));
+    /// ```
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Value),
+            2 => Ok(Self::Identity),
+            x => Err(InvalidTag(x)),
+        }
+    }
+}
+
+/// Describes the elements of values of a `ValueType::Tuple` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TupleType {
+    Homogeneous(ValueType),        // Any number of elements, all of the same type.
+    Heterogeneous(Vec<ValueType>), // A fixed number of elements, each with its own type.
+    Composite(Vec<String>),        // Derived by the transactor from the values of other attributes.
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Attribute {
     pub id: u64,
@@ -106,7 +180,13 @@ pub struct AttributeDefinition {
     pub value_type: ValueType,
     pub cardinality: Cardinality,
     pub doc: Option<String>,
-    pub unique: bool,
+    pub unique: Option<Unique>,
+    pub is_component: bool,
+    pub verify_refs: bool,
+    pub index: bool,
+    pub no_history: bool,
+    pub preds: Vec<String>, // Names of predicates registered in `TransactorConfig`.
+    pub tuple_type: Option<TupleType>,
 }
 
 impl AttributeDefinition {
@@ -116,10 +196,43 @@ impl AttributeDefinition {
             value_type,
             cardinality: Cardinality::One,
             doc: None,
-            unique: false,
+            unique: None,
+            is_component: false,
+            verify_refs: false,
+            index: false,
+            no_history: false,
+            preds: Vec::new(),
+            tuple_type: None,
         }
     }
 
+    /// Creates an attribute whose values are tuples of any length, with elements of `value_type`
+    /// or nil.
+    pub fn homogeneous_tuple(ident: &str, value_type: ValueType) -> Self {
+        let mut attribute = Self::new(ident, ValueType::Tuple);
+        attribute.tuple_type = Some(TupleType::Homogeneous(value_type));
+        attribute
+    }
+
+    /// Creates an attribute whose values are tuples with an element of each of `value_types`, or
+    /// nil.
+    pub fn heterogeneous_tuple(ident: &str, value_types: &[ValueType]) -> Self {
+        let mut attribute = Self::new(ident, ValueType::Tuple);
+        attribute.tuple_type = Some(TupleType::Heterogeneous(value_types.to_vec()));
+        attribute
+    }
+
+    /// Creates an attribute whose values are maintained by the transactor: whenever one of
+    /// `attributes` changes, the entity gets a tuple of its current values of all of them. These
+    /// tuples can't be asserted directly. Making the attribute unique makes the combination of
+    /// values unique.
+    pub fn composite_tuple(ident: &str, attributes: &[&str]) -> Self {
+        let mut attribute = Self::new(ident, ValueType::Tuple);
+        let attributes = attributes.iter().map(|ident| ident.to_string()).collect();
+        attribute.tuple_type = Some(TupleType::Composite(attributes));
+        attribute
+    }
+
     pub fn with_doc(mut self, doc: &str) -> Self {
         self.doc = Some(doc.to_string());
         self
@@ -131,7 +244,51 @@ impl AttributeDefinition {
     }
 
     pub fn unique(mut self) -> Self {
-        self.unique = true;
+        self.unique = Some(Unique::Value);
+        self
+    }
+
+    /// Makes the attribute's values identify their entities. Asserting an existing value for a
+    /// new entity resolves to the entity which already has it, rather than failing the
+    /// transaction.
+    pub fn unique_identity(mut self) -> Self {
+        self.unique = Some(Unique::Identity);
+        self
+    }
+
+    /// Marks a reference attribute as pointing to a component: an entity which is owned by the
+    /// referencing entity, and is retracted along with it.
+    pub fn component(mut self) -> Self {
+        self.is_component = true;
+        self
+    }
+
+    /// Makes transactions fail when a value of this reference attribute points to an entity
+    /// which neither exists in the database nor is created by the transaction.
+    pub fn verify_refs(mut self) -> Self {
+        self.verify_refs = true;
+        self
+    }
+
+    /// Maintains the AVET index for this attribute, for efficient lookups by value. Unique
+    /// attributes are always indexed.
+    pub fn index(mut self) -> Self {
+        self.index = true;
+        self
+    }
+
+    /// Stops keeping the history of this attribute: storages drop values as soon as they are
+    /// retracted or replaced, so they are not visible to historical queries. Useful for high
+    /// churn attributes, such as counters.
+    pub fn no_history(mut self) -> Self {
+        self.no_history = true;
+        self
+    }
+
+    /// Makes transactions fail when an asserted value doesn't satisfy the predicate registered as
+    /// `predicate`, see `TransactorConfig::with_predicate`. Can be used multiple times.
+    pub fn with_predicate(mut self, predicate: &str) -> Self {
+        self.preds.push(predicate.to_string());
         self
     }
 }
@@ -139,14 +296,197 @@ impl AttributeDefinition {
 impl From<AttributeDefinition> for tx::EntityOperation {
     fn from(attribute: AttributeDefinition) -> Self {
         let mut operation = Self::on_new()
+            .in_partition(Partition::DB)
             .assert(DB_ATTR_IDENT_IDENT, attribute.ident)
             .assert(DB_ATTR_CARDINALITY_IDENT, attribute.cardinality as u64)
             .assert(DB_ATTR_TYPE_IDENT, attribute.value_type as u64);
         if let Some(doc) = attribute.doc {
             operation = operation.assert(DB_ATTR_DOC_IDENT, doc);
         }
-        if attribute.unique {
-            operation = operation.assert(DB_ATTR_UNIQUE_IDENT, 1u64);
+        if let Some(unique) = attribute.unique {
+            operation = operation.assert(DB_ATTR_UNIQUE_IDENT, unique as u64);
+        }
+        if attribute.is_component {
+            operation = operation.assert(DB_ATTR_COMPONENT_IDENT, 1u64);
+        }
+        if attribute.verify_refs {
+            operation = operation.assert(DB_ATTR_VERIFY_REFS_IDENT, 1u64);
+        }
+        if attribute.index {
+            operation = operation.assert(DB_ATTR_INDEX_IDENT, 1u64);
+        }
+        if attribute.no_history {
+            operation = operation.assert(DB_ATTR_NO_HISTORY_IDENT, 1u64);
+        }
+        for predicate in attribute.preds {
+            operation = operation.assert(DB_ATTR_PREDS_IDENT, Value::Keyword(predicate));
+        }
+        match attribute.tuple_type {
+            Some(TupleType::Homogeneous(value_type)) => {
+                operation = operation.assert(DB_ATTR_TUPLE_TYPE_IDENT, value_type as u64);
+            }
+            Some(TupleType::Heterogeneous(value_types)) => {
+                let value_types = value_types
+                    .into_iter()
+                    .map(|value_type| Value::U64(value_type as u64))
+                    .collect();
+                operation = operation.assert(DB_ATTR_TUPLE_TYPES_IDENT, Value::Tuple(value_types));
+            }
+            Some(TupleType::Composite(attributes)) => {
+                let attributes = attributes.into_iter().map(Value::Keyword).collect();
+                operation = operation.assert(DB_ATTR_TUPLE_ATTRS_IDENT, Value::Tuple(attributes));
+            }
+            None => (),
+        }
+        operation
+    }
+}
+
+/// Alters an installed attribute, identified by its current ident. Alterations are validated when
+/// transacted: the value type can't be changed, cardinality can only become `Cardinality::One` if
+/// no entity has multiple values, and the attribute can only become unique if its values are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeAlteration {
+    pub ident: String,
+    pub new_ident: Option<String>,
+    pub cardinality: Option<Cardinality>,
+    pub doc: Option<String>,
+    pub unique: Option<Option<Unique>>,
+    pub index: Option<bool>,
+    pub no_history: Option<bool>,
+    pub added_preds: Vec<String>,
+    pub removed_preds: Vec<String>,
+}
+
+impl AttributeAlteration {
+    pub fn new(ident: &str) -> Self {
+        Self {
+            ident: ident.to_string(),
+            new_ident: None,
+            cardinality: None,
+            doc: None,
+            unique: None,
+            index: None,
+            no_history: None,
+            added_preds: Vec::new(),
+            removed_preds: Vec::new(),
+        }
+    }
+
+    /// Renames the attribute. The previous ident keeps resolving to the attribute, unless it is
+    /// reused by another attribute.
+    pub fn rename(mut self, new_ident: &str) -> Self {
+        self.new_ident = Some(new_ident.to_string());
+        self
+    }
+
+    pub fn with_doc(mut self, doc: &str) -> Self {
+        self.doc = Some(doc.to_string());
+        self
+    }
+
+    pub fn one(mut self) -> Self {
+        self.cardinality = Some(Cardinality::One);
+        self
+    }
+
+    pub fn many(mut self) -> Self {
+        self.cardinality = Some(Cardinality::Many);
+        self
+    }
+
+    pub fn unique(mut self) -> Self {
+        self.unique = Some(Some(Unique::Value));
+        self
+    }
+
+    pub fn unique_identity(mut self) -> Self {
+        self.unique = Some(Some(Unique::Identity));
+        self
+    }
+
+    pub fn not_unique(mut self) -> Self {
+        self.unique = Some(None);
+        self
+    }
+
+    /// Starts maintaining the AVET index for the attribute, which is backfilled with its
+    /// existing values.
+    pub fn index(mut self) -> Self {
+        self.index = Some(true);
+        self
+    }
+
+    pub fn not_indexed(mut self) -> Self {
+        self.index = Some(false);
+        self
+    }
+
+    /// Stops keeping the history of the attribute. Existing history is kept until it is purged.
+    pub fn no_history(mut self) -> Self {
+        self.no_history = Some(true);
+        self
+    }
+
+    pub fn keep_history(mut self) -> Self {
+        self.no_history = Some(false);
+        self
+    }
+
+    /// Adds a predicate which values asserted from now on must satisfy. Existing values are not
+    /// validated.
+    pub fn with_predicate(mut self, predicate: &str) -> Self {
+        self.added_preds.push(predicate.to_string());
+        self
+    }
+
+    pub fn without_predicate(mut self, predicate: &str) -> Self {
+        self.removed_preds.push(predicate.to_string());
+        self
+    }
+}
+
+impl From<AttributeAlteration> for tx::EntityOperation {
+    fn from(alteration: AttributeAlteration) -> Self {
+        let mut operation = Self::on_lookup_ref(DB_ATTR_IDENT_IDENT, alteration.ident.as_str());
+        if let Some(new_ident) = alteration.new_ident {
+            operation = operation
+                .assert(DB_ATTR_IDENT_IDENT, new_ident)
+                .assert(DB_ATTR_ALIAS_IDENT, alteration.ident);
+        }
+        if let Some(cardinality) = alteration.cardinality {
+            operation = operation.assert(DB_ATTR_CARDINALITY_IDENT, cardinality as u64);
+        }
+        if let Some(doc) = alteration.doc {
+            operation = operation.assert(DB_ATTR_DOC_IDENT, doc);
+        }
+        match alteration.unique {
+            Some(Some(unique)) => {
+                operation = operation.assert(DB_ATTR_UNIQUE_IDENT, unique as u64);
+            }
+            Some(None) => {
+                // Only the current value is actually retracted, the other one is redundant.
+                operation = operation
+                    .retract(DB_ATTR_UNIQUE_IDENT, Unique::Value as u64)
+                    .retract(DB_ATTR_UNIQUE_IDENT, Unique::Identity as u64);
+            }
+            None => (),
+        }
+        match alteration.index {
+            Some(true) => operation = operation.assert(DB_ATTR_INDEX_IDENT, 1u64),
+            Some(false) => operation = operation.retract(DB_ATTR_INDEX_IDENT, 1u64),
+            None => (),
+        }
+        match alteration.no_history {
+            Some(true) => operation = operation.assert(DB_ATTR_NO_HISTORY_IDENT, 1u64),
+            Some(false) => operation = operation.retract(DB_ATTR_NO_HISTORY_IDENT, 1u64),
+            None => (),
+        }
+        for predicate in alteration.added_preds {
+            operation = operation.assert(DB_ATTR_PREDS_IDENT, Value::Keyword(predicate));
+        }
+        for predicate in alteration.removed_preds {
+            operation = operation.retract(DB_ATTR_PREDS_IDENT, Value::Keyword(predicate));
         }
         operation
     }
@@ -13,7 +13,7 @@ pub fn default_datoms() -> Vec<Datom> {
         Datom::add(DB_ATTR_IDENT_ID, DB_ATTR_DOC_ID, "Human readable name of attribute", tx),
         Datom::add(DB_ATTR_IDENT_ID, DB_ATTR_TYPE_ID, ValueType::Str as u64, tx),
         Datom::add(DB_ATTR_IDENT_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
-        Datom::add(DB_ATTR_IDENT_ID, DB_ATTR_UNIQUE_ID, 1u64, tx),
+        Datom::add(DB_ATTR_IDENT_ID, DB_ATTR_UNIQUE_ID, Unique::Value as u64, tx),
         // "db/attr/doc" attribute
         Datom::add(DB_ATTR_DOC_ID, DB_ATTR_IDENT_ID, DB_ATTR_DOC_IDENT, tx),
         Datom::add(DB_ATTR_DOC_ID, DB_ATTR_DOC_ID, "Documentation of attribute", tx),
@@ -39,5 +39,85 @@ pub fn default_datoms() -> Vec<Datom> {
         Datom::add(DB_TX_TIME_ID, DB_ATTR_DOC_ID, "Transaction's wall clock time", tx),
         Datom::add(DB_TX_TIME_ID, DB_ATTR_TYPE_ID, ValueType::U64 as u64, tx),
         Datom::add(DB_TX_TIME_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        // "db/attr/component" attribute
+        Datom::add(DB_ATTR_COMPONENT_ID, DB_ATTR_IDENT_ID, DB_ATTR_COMPONENT_IDENT, tx),
+        Datom::add(DB_ATTR_COMPONENT_ID, DB_ATTR_DOC_ID, "Indicates this attribute references a component entity", tx),
+        Datom::add(DB_ATTR_COMPONENT_ID, DB_ATTR_TYPE_ID, ValueType::U64 as u64, tx),
+        Datom::add(DB_ATTR_COMPONENT_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        // "db/attr/verifyRefs" attribute
+        Datom::add(DB_ATTR_VERIFY_REFS_ID, DB_ATTR_IDENT_ID, DB_ATTR_VERIFY_REFS_IDENT, tx),
+        Datom::add(DB_ATTR_VERIFY_REFS_ID, DB_ATTR_DOC_ID, "Indicates referenced entities of this attribute must exist", tx),
+        Datom::add(DB_ATTR_VERIFY_REFS_ID, DB_ATTR_TYPE_ID, ValueType::U64 as u64, tx),
+        Datom::add(DB_ATTR_VERIFY_REFS_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        // "db/attr/alias" attribute
+        Datom::add(DB_ATTR_ALIAS_ID, DB_ATTR_IDENT_ID, DB_ATTR_ALIAS_IDENT, tx),
+        Datom::add(DB_ATTR_ALIAS_ID, DB_ATTR_DOC_ID, "Previous idents of a renamed attribute", tx),
+        Datom::add(DB_ATTR_ALIAS_ID, DB_ATTR_TYPE_ID, ValueType::Str as u64, tx),
+        Datom::add(DB_ATTR_ALIAS_ID, DB_ATTR_CARDINALITY_ID, Cardinality::Many as u64, tx),
+        Datom::add(DB_ATTR_ALIAS_ID, DB_ATTR_INDEX_ID, 1u64, tx),
+        // "db/attr/tupleType" attribute
+        Datom::add(DB_ATTR_TUPLE_TYPE_ID, DB_ATTR_IDENT_ID, DB_ATTR_TUPLE_TYPE_IDENT, tx),
+        Datom::add(DB_ATTR_TUPLE_TYPE_ID, DB_ATTR_DOC_ID, "Value type of all elements of a homogeneous tuple", tx),
+        Datom::add(DB_ATTR_TUPLE_TYPE_ID, DB_ATTR_TYPE_ID, ValueType::U64 as u64, tx),
+        Datom::add(DB_ATTR_TUPLE_TYPE_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        // "db/attr/tupleTypes" attribute
+        Datom::add(DB_ATTR_TUPLE_TYPES_ID, DB_ATTR_IDENT_ID, DB_ATTR_TUPLE_TYPES_IDENT, tx),
+        Datom::add(DB_ATTR_TUPLE_TYPES_ID, DB_ATTR_DOC_ID, "Value types of the elements of a heterogeneous tuple", tx),
+        Datom::add(DB_ATTR_TUPLE_TYPES_ID, DB_ATTR_TYPE_ID, ValueType::Tuple as u64, tx),
+        Datom::add(DB_ATTR_TUPLE_TYPES_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        Datom::add(DB_ATTR_TUPLE_TYPES_ID, DB_ATTR_TUPLE_TYPE_ID, ValueType::U64 as u64, tx),
+        // "db/attr/tupleAttrs" attribute
+        Datom::add(DB_ATTR_TUPLE_ATTRS_ID, DB_ATTR_IDENT_ID, DB_ATTR_TUPLE_ATTRS_IDENT, tx),
+        Datom::add(DB_ATTR_TUPLE_ATTRS_ID, DB_ATTR_DOC_ID, "Attributes whose values make up a composite tuple", tx),
+        Datom::add(DB_ATTR_TUPLE_ATTRS_ID, DB_ATTR_TYPE_ID, ValueType::Tuple as u64, tx),
+        Datom::add(DB_ATTR_TUPLE_ATTRS_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        Datom::add(DB_ATTR_TUPLE_ATTRS_ID, DB_ATTR_TUPLE_TYPE_ID, ValueType::Keyword as u64, tx),
+        // "db/attr/index" attribute
+        Datom::add(DB_ATTR_INDEX_ID, DB_ATTR_IDENT_ID, DB_ATTR_INDEX_IDENT, tx),
+        Datom::add(DB_ATTR_INDEX_ID, DB_ATTR_DOC_ID, "Indicates the AVET index is maintained for this attribute", tx),
+        Datom::add(DB_ATTR_INDEX_ID, DB_ATTR_TYPE_ID, ValueType::U64 as u64, tx),
+        Datom::add(DB_ATTR_INDEX_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        // "db/attr/noHistory" attribute
+        Datom::add(DB_ATTR_NO_HISTORY_ID, DB_ATTR_IDENT_ID, DB_ATTR_NO_HISTORY_IDENT, tx),
+        Datom::add(DB_ATTR_NO_HISTORY_ID, DB_ATTR_DOC_ID, "Indicates that superseded values of this attribute are not kept", tx),
+        Datom::add(DB_ATTR_NO_HISTORY_ID, DB_ATTR_TYPE_ID, ValueType::U64 as u64, tx),
+        Datom::add(DB_ATTR_NO_HISTORY_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        // "db/excise" attribute
+        Datom::add(DB_EXCISE_ID, DB_ATTR_IDENT_ID, DB_EXCISE_IDENT, tx),
+        Datom::add(DB_EXCISE_ID, DB_ATTR_DOC_ID, "Entity whose datoms were permanently removed", tx),
+        Datom::add(DB_EXCISE_ID, DB_ATTR_TYPE_ID, ValueType::Ref as u64, tx),
+        Datom::add(DB_EXCISE_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        // "db/excise/attrs" attribute
+        Datom::add(DB_EXCISE_ATTRS_ID, DB_ATTR_IDENT_ID, DB_EXCISE_ATTRS_IDENT, tx),
+        Datom::add(DB_EXCISE_ATTRS_ID, DB_ATTR_DOC_ID, "Attributes whose values were removed by an excision, all if none", tx),
+        Datom::add(DB_EXCISE_ATTRS_ID, DB_ATTR_TYPE_ID, ValueType::Ref as u64, tx),
+        Datom::add(DB_EXCISE_ATTRS_ID, DB_ATTR_CARDINALITY_ID, Cardinality::Many as u64, tx),
+        // "db/attr/preds" attribute
+        Datom::add(DB_ATTR_PREDS_ID, DB_ATTR_IDENT_ID, DB_ATTR_PREDS_IDENT, tx),
+        Datom::add(DB_ATTR_PREDS_ID, DB_ATTR_DOC_ID, "Names of predicates which asserted values of this attribute must satisfy", tx),
+        Datom::add(DB_ATTR_PREDS_ID, DB_ATTR_TYPE_ID, ValueType::Keyword as u64, tx),
+        Datom::add(DB_ATTR_PREDS_ID, DB_ATTR_CARDINALITY_ID, Cardinality::Many as u64, tx),
+        // "db/spec/ident" attribute
+        Datom::add(DB_SPEC_IDENT_ID, DB_ATTR_IDENT_ID, DB_SPEC_IDENT_IDENT, tx),
+        Datom::add(DB_SPEC_IDENT_ID, DB_ATTR_DOC_ID, "Keyword identifying an entity spec, like attribute idents", tx),
+        Datom::add(DB_SPEC_IDENT_ID, DB_ATTR_TYPE_ID, ValueType::Keyword as u64, tx),
+        Datom::add(DB_SPEC_IDENT_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        Datom::add(DB_SPEC_IDENT_ID, DB_ATTR_UNIQUE_ID, Unique::Value as u64, tx),
+        // "db/spec/attrs" attribute
+        Datom::add(DB_SPEC_ATTRS_ID, DB_ATTR_IDENT_ID, DB_SPEC_ATTRS_IDENT, tx),
+        Datom::add(DB_SPEC_ATTRS_ID, DB_ATTR_DOC_ID, "Attributes required by entity spec", tx),
+        Datom::add(DB_SPEC_ATTRS_ID, DB_ATTR_TYPE_ID, ValueType::Keyword as u64, tx),
+        Datom::add(DB_SPEC_ATTRS_ID, DB_ATTR_CARDINALITY_ID, Cardinality::Many as u64, tx),
+        // "db/ensure" attribute
+        Datom::add(DB_ENSURE_ID, DB_ATTR_IDENT_ID, DB_ENSURE_IDENT, tx),
+        Datom::add(DB_ENSURE_ID, DB_ATTR_DOC_ID, "Entity spec to validate the entity against, never saved", tx),
+        Datom::add(DB_ENSURE_ID, DB_ATTR_TYPE_ID, ValueType::Ref as u64, tx),
+        Datom::add(DB_ENSURE_ID, DB_ATTR_CARDINALITY_ID, Cardinality::Many as u64, tx),
+        // "db/ident" attribute
+        Datom::add(DB_IDENT_ID, DB_ATTR_IDENT_ID, DB_IDENT_IDENT, tx),
+        Datom::add(DB_IDENT_ID, DB_ATTR_DOC_ID, "Keyword identifying an entity, such as an enum value, which references can use instead of its ID", tx),
+        Datom::add(DB_IDENT_ID, DB_ATTR_TYPE_ID, ValueType::Keyword as u64, tx),
+        Datom::add(DB_IDENT_ID, DB_ATTR_CARDINALITY_ID, Cardinality::One as u64, tx),
+        Datom::add(DB_IDENT_ID, DB_ATTR_UNIQUE_ID, Unique::Identity as u64, tx),
     ]
 }
@@ -1,5 +1,7 @@
 pub mod attribute;
 pub mod default;
+pub mod partition;
+pub mod spec;
 
 pub const DB_ATTR_IDENT_IDENT: &str = "db/attr/ident";
 pub const DB_ATTR_IDENT_ID: u64 = 1;
@@ -18,3 +20,48 @@ pub const DB_ATTR_UNIQUE_ID: u64 = 5;
 
 pub const DB_TX_TIME_IDENT: &str = "db/tx/time";
 pub const DB_TX_TIME_ID: u64 = 6;
+
+pub const DB_ATTR_COMPONENT_IDENT: &str = "db/attr/component";
+pub const DB_ATTR_COMPONENT_ID: u64 = 7;
+
+pub const DB_ATTR_VERIFY_REFS_IDENT: &str = "db/attr/verifyRefs";
+pub const DB_ATTR_VERIFY_REFS_ID: u64 = 8;
+
+pub const DB_ATTR_ALIAS_IDENT: &str = "db/attr/alias";
+pub const DB_ATTR_ALIAS_ID: u64 = 9;
+
+pub const DB_ATTR_TUPLE_TYPE_IDENT: &str = "db/attr/tupleType";
+pub const DB_ATTR_TUPLE_TYPE_ID: u64 = 10;
+
+pub const DB_ATTR_TUPLE_TYPES_IDENT: &str = "db/attr/tupleTypes";
+pub const DB_ATTR_TUPLE_TYPES_ID: u64 = 11;
+
+pub const DB_ATTR_TUPLE_ATTRS_IDENT: &str = "db/attr/tupleAttrs";
+pub const DB_ATTR_TUPLE_ATTRS_ID: u64 = 12;
+
+pub const DB_ATTR_INDEX_IDENT: &str = "db/attr/index";
+pub const DB_ATTR_INDEX_ID: u64 = 13;
+
+pub const DB_ATTR_NO_HISTORY_IDENT: &str = "db/attr/noHistory";
+pub const DB_ATTR_NO_HISTORY_ID: u64 = 14;
+
+pub const DB_EXCISE_IDENT: &str = "db/excise";
+pub const DB_EXCISE_ID: u64 = 15;
+
+pub const DB_EXCISE_ATTRS_IDENT: &str = "db/excise/attrs";
+pub const DB_EXCISE_ATTRS_ID: u64 = 16;
+
+pub const DB_ATTR_PREDS_IDENT: &str = "db/attr/preds";
+pub const DB_ATTR_PREDS_ID: u64 = 17;
+
+pub const DB_SPEC_IDENT_IDENT: &str = "db/spec/ident";
+pub const DB_SPEC_IDENT_ID: u64 = 18;
+
+pub const DB_SPEC_ATTRS_IDENT: &str = "db/spec/attrs";
+pub const DB_SPEC_ATTRS_ID: u64 = 19;
+
+pub const DB_ENSURE_IDENT: &str = "db/ensure";
+pub const DB_ENSURE_ID: u64 = 20;
+
+pub const DB_IDENT_IDENT: &str = "db/ident";
+pub const DB_IDENT_ID: u64 = 21;
@@ -0,0 +1,91 @@
+/// Number of low bits of an entity ID which hold its position in the ID sequence. The remaining
+/// high bits hold the partition of the entity.
+pub const ENTITY_BITS: u32 = 42;
+
+const ENTITY_MASK: u64 = (1 << ENTITY_BITS) - 1;
+
+/// A partition groups entities by their ID prefix, such that entities of the same partition are
+/// stored next to each other in the `Eavt` index. All partitions share a single ID sequence, so
+/// entity IDs still increase with the transactions that created them.
+///
+/// | Partition  | Entities                               |
+/// |------------|----------------------------------------|
+/// | `DB`       | Attributes and other schema entities   |
+/// | `TX`       | Transactions                           |
+/// | `USER`     | Default for entities created by users  |
+/// | `custom(n)`| Application defined                    |
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Partition(u64);
+
+impl Partition {
+    pub const DB: Self = Self(0);
+    pub const TX: Self = Self(1);
+    pub const USER: Self = Self(2);
+
+    /// Application defined partition. Custom partitions are numbered after the built-in ones.
+    pub const fn custom(index: u16) -> Self {
+        Self(Self::USER.0 + 1 + index as u64)
+    }
+
+    /// Returns the partition of `entity`.
+    pub const fn of(entity: u64) -> Self {
+        Self(entity >> ENTITY_BITS)
+    }
+
+    /// Returns the ID in this partition at position `counter` of the ID sequence.
+    pub const fn entity(self, counter: u64) -> u64 {
+        self.0 << ENTITY_BITS | counter
+    }
+}
+
+impl Default for Partition {
+    fn default() -> Self {
+        Self::USER
+    }
+}
+
+/// Returns the position of `entity` in the ID sequence, regardless of its partition.
+pub const fn counter(entity: u64) -> u64 {
+    entity & ENTITY_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::DB_IDENT_ID;
+
+    #[test]
+    fn encode_partition_in_high_bits() {
+        let tx = Partition::TX.entity(42);
+        let user = Partition::USER.entity(43);
+
+        assert_eq!(Partition::TX, Partition::of(tx));
+        assert_eq!(Partition::USER, Partition::of(user));
+        assert_eq!(42, counter(tx));
+        assert_eq!(43, counter(user));
+        assert!(tx < user);
+    }
+
+    #[test]
+    fn schema_ids_are_in_db_partition() {
+        assert_eq!(Partition::DB, Partition::of(DB_IDENT_ID));
+        assert_eq!(DB_IDENT_ID, Partition::DB.entity(DB_IDENT_ID));
+    }
+
+    #[test]
+    fn custom_partitions_are_distinct() {
+        let partitions = [
+            Partition::DB,
+            Partition::TX,
+            Partition::USER,
+            Partition::custom(0),
+            Partition::custom(1),
+        ];
+
+        for (i, a) in partitions.iter().enumerate() {
+            for b in &partitions[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::datom::Value;
+use crate::schema::partition::Partition;
+use crate::schema::*;
+use crate::tx;
+
+/// Describes a kind of entity by the attributes it must have. Entities are validated against a
+/// spec when it is ensured on them, see `EntityOperation::ensure`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntitySpec {
+    pub ident: String,
+    pub attributes: Vec<String>,
+}
+
+impl EntitySpec {
+    pub fn new(ident: &str) -> Self {
+        Self {
+            ident: ident.to_string(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn require(mut self, attribute: &str) -> Self {
+        self.attributes.push(attribute.to_string());
+        self
+    }
+}
+
+impl From<EntitySpec> for tx::EntityOperation {
+    fn from(spec: EntitySpec) -> Self {
+        let mut operation = Self::on_new()
+            .in_partition(Partition::DB)
+            .assert(DB_SPEC_IDENT_IDENT, Value::Keyword(spec.ident));
+        for attribute in spec.attributes {
+            operation = operation.assert(DB_SPEC_ATTRS_IDENT, Value::Keyword(attribute));
+        }
+        operation
+    }
+}
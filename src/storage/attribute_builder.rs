@@ -9,7 +9,13 @@ pub struct AttributeBuilder {
     value_type: Option<ValueType>,
     cardinality: Option<Cardinality>,
     doc: Option<String>,
-    unique: bool,
+    unique: Option<Unique>,
+    is_component: bool,
+    verify_refs: bool,
+    index: bool,
+    no_history: bool,
+    preds: Vec<String>,
+    tuple_type: Option<TupleType>,
 }
 
 impl AttributeBuilder {
@@ -21,12 +27,22 @@ impl AttributeBuilder {
             value_type: None,
             cardinality: None,
             doc: None,
-            unique: false,
+            unique: None,
+            is_component: false,
+            verify_refs: false,
+            index: false,
+            no_history: false,
+            preds: Vec::new(),
+            tuple_type: None,
         }
     }
 
+    /// Records a change of the attribute at `tx`. The latest change is the attribute's version.
+    pub fn altered_at(&mut self, tx: u64) {
+        self.version = self.version.max(tx);
+    }
+
     pub fn consume(&mut self, datom: Datom) {
-        self.version = self.version.max(datom.tx);
         match datom {
             Datom {
                 attribute: DB_ATTR_IDENT_ID,
@@ -50,9 +66,71 @@ impl AttributeBuilder {
             } => self.doc = Some(doc.to_string()),
             Datom {
                 attribute: DB_ATTR_UNIQUE_ID,
+                value: Value::U64(unique),
+                ..
+            } => self.unique = Unique::try_from(unique).ok(),
+            Datom {
+                attribute: DB_ATTR_COMPONENT_ID,
+                value: Value::U64(1),
+                ..
+            } => self.is_component = true,
+            Datom {
+                attribute: DB_ATTR_VERIFY_REFS_ID,
+                value: Value::U64(1),
+                ..
+            } => self.verify_refs = true,
+            Datom {
+                attribute: DB_ATTR_INDEX_ID,
+                value: Value::U64(1),
+                ..
+            } => self.index = true,
+            Datom {
+                attribute: DB_ATTR_NO_HISTORY_ID,
                 value: Value::U64(1),
                 ..
-            } => self.unique = true,
+            } => self.no_history = true,
+            Datom {
+                attribute: DB_ATTR_PREDS_ID,
+                value: Value::Keyword(predicate),
+                ..
+            } => self.preds.push(predicate),
+            Datom {
+                attribute: DB_ATTR_TUPLE_TYPE_ID,
+                value: Value::U64(value_type),
+                ..
+            } => {
+                self.tuple_type = ValueType::try_from(value_type)
+                    .ok()
+                    .map(TupleType::Homogeneous)
+            }
+            Datom {
+                attribute: DB_ATTR_TUPLE_TYPES_ID,
+                value: Value::Tuple(value_types),
+                ..
+            } => {
+                self.tuple_type = value_types
+                    .into_iter()
+                    .map(|value_type| match value_type {
+                        Value::U64(value_type) => ValueType::try_from(value_type).ok(),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()
+                    .map(TupleType::Heterogeneous)
+            }
+            Datom {
+                attribute: DB_ATTR_TUPLE_ATTRS_ID,
+                value: Value::Tuple(attributes),
+                ..
+            } => {
+                self.tuple_type = attributes
+                    .into_iter()
+                    .map(|attribute| match attribute {
+                        Value::Keyword(ident) => Some(ident),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()
+                    .map(TupleType::Composite)
+            }
             _ => (),
         }
     }
@@ -70,6 +148,12 @@ impl AttributeBuilder {
                 cardinality,
                 doc: self.doc,
                 unique: self.unique,
+                is_component: self.is_component,
+                verify_refs: self.verify_refs,
+                index: self.index,
+                no_history: self.no_history,
+                preds: self.preds,
+                tuple_type: self.tuple_type,
             },
         })
     }
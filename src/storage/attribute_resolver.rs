@@ -1,9 +1,11 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::RwLock;
 
 use crate::datom::*;
+use crate::query::pattern::AttributeIdentifier;
 use crate::schema::attribute::*;
 use crate::schema::*;
 use crate::storage::attribute_builder::*;
@@ -11,9 +13,21 @@ use crate::storage::ReadStorage;
 
 use super::Restricts;
 
+/// Resolves attributes by ident or ID, caching the latest version of each resolved attribute.
+/// Attributes resolved at a basis preceding their latest version are read from storage, such that
+/// historical queries see the schema as it was. Entities with a `db/ident`, such as enum values,
+/// are resolved and cached in the same way.
 #[derive(Default)]
 pub struct AttributeResolver {
     cache: Arc<RwLock<HashMap<String, Arc<Attribute>>>>,
+    cache_by_id: Arc<RwLock<HashMap<u64, Arc<Attribute>>>>,
+    entities: Arc<RwLock<HashMap<String, IdentifiedEntity>>>,
+}
+
+#[derive(Clone, Copy)]
+struct IdentifiedEntity {
+    id: u64,
+    version: u64, // Transaction which assigned the ident
 }
 
 impl AttributeResolver {
@@ -21,26 +35,135 @@ impl AttributeResolver {
         Self::default()
     }
 
+    pub async fn resolve_identifier<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        identifier: &AttributeIdentifier,
+        tx: u64,
+    ) -> Result<Arc<Attribute>, ResolveError<S::Error>> {
+        match identifier {
+            AttributeIdentifier::Ident(ident) => self.resolve(storage, ident, tx).await,
+            AttributeIdentifier::Id(id) => self.resolve_id(storage, *id, tx).await,
+        }
+    }
+
     pub async fn resolve<'a, S: ReadStorage<'a>>(
         &self,
         storage: &'a S,
         ident: &str,
         tx: u64,
     ) -> Result<Arc<Attribute>, ResolveError<S::Error>> {
-        {
-            let cache_read = self.cache.read().await;
-            if let Some(attribute) = cache_read.get(ident) {
-                return Ok(attribute.clone());
+        let cached = self.cache.read().await.get(ident).cloned();
+        if let Some(attribute) = cached {
+            if attribute.version <= tx {
+                return Ok(attribute);
+            }
+        } else if let Some(attribute) = resolve_by_ident(storage, ident, u64::MAX)? {
+            if attribute.version <= tx {
+                let mut cache_write = self.cache.write().await;
+                cache_write.insert(ident.to_string(), attribute.clone());
+                return Ok(attribute);
             }
         }
 
-        if let Some(attribute) = resolve_by_ident(storage, ident, tx)? {
-            let mut cache_write = self.cache.write().await;
-            cache_write.insert(ident.to_string(), attribute.clone());
-            return Ok(attribute);
+        // The attribute was altered after `tx`, resolve the version which was current at `tx`.
+        match resolve_by_ident(storage, ident, tx)? {
+            Some(attribute) => Ok(attribute),
+            None => Err(ResolveError::IdentNotFound(ident.to_string())),
+        }
+    }
+
+    pub async fn resolve_id<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        attribute_id: u64,
+        tx: u64,
+    ) -> Result<Arc<Attribute>, ResolveError<S::Error>> {
+        let cached = self.cache_by_id.read().await.get(&attribute_id).cloned();
+        if let Some(attribute) = cached {
+            if attribute.version <= tx {
+                return Ok(attribute);
+            }
+        } else if let Some(attribute) = resolve_by_id(storage, attribute_id, u64::MAX)? {
+            if attribute.version <= tx {
+                let mut cache_write = self.cache_by_id.write().await;
+                cache_write.insert(attribute_id, attribute.clone());
+                return Ok(attribute);
+            }
         }
 
-        Err(ResolveError::IdentNotFound(ident.to_string()))
+        // The attribute was altered after `tx`, resolve the version which was current at `tx`.
+        match resolve_by_id(storage, attribute_id, tx)? {
+            Some(attribute) => Ok(attribute),
+            None => Err(ResolveError::IdNotFound(attribute_id)),
+        }
+    }
+
+    /// Resolves the ID of the entity whose `db/ident` is `ident` at `tx`.
+    pub async fn resolve_entity<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        ident: &str,
+        tx: u64,
+    ) -> Result<u64, ResolveError<S::Error>> {
+        let cached = self.entities.read().await.get(ident).copied();
+        if let Some(entity) = cached {
+            if entity.version <= tx {
+                return Ok(entity.id);
+            }
+        } else if let Some(entity) = resolve_entity_by_ident(storage, ident, u64::MAX)? {
+            if entity.version <= tx {
+                let mut cache_write = self.entities.write().await;
+                cache_write.insert(ident.to_string(), entity);
+                return Ok(entity.id);
+            }
+        }
+
+        // The ident was assigned after `tx`, resolve the entity which had it at `tx`.
+        match resolve_entity_by_ident(storage, ident, tx)? {
+            Some(entity) => Ok(entity.id),
+            None => Err(ResolveError::IdentNotFound(ident.to_string())),
+        }
+    }
+
+    /// Evicts cached attributes which are altered by `datoms`, and entities whose idents are
+    /// changed or which are excised, such that they are resolved from storage.
+    pub async fn invalidate(&self, datoms: &[Datom]) {
+        let idents: HashSet<_> = datoms
+            .iter()
+            .filter(|datom| datom.attribute == DB_IDENT_ID)
+            .filter_map(|datom| match &datom.value {
+                Value::Keyword(ident) => Some(ident),
+                _ => None,
+            })
+            .collect();
+        // Excised datoms are removed by storage without being retracted.
+        let excised: HashSet<_> = datoms
+            .iter()
+            .filter(|datom| datom.attribute == DB_EXCISE_ID)
+            .filter_map(|datom| match datom.value {
+                Value::Ref(entity) => Some(entity),
+                _ => None,
+            })
+            .collect();
+        if !idents.is_empty() || !excised.is_empty() {
+            let mut cache_write = self.entities.write().await;
+            cache_write
+                .retain(|ident, entity| !idents.contains(ident) && !excised.contains(&entity.id));
+        }
+
+        let altered: HashSet<_> = datoms
+            .iter()
+            .filter(|datom| is_schema_attribute(datom.attribute))
+            .map(|datom| datom.entity)
+            .collect();
+        if altered.is_empty() {
+            return;
+        }
+        let mut cache_write = self.cache.write().await;
+        cache_write.retain(|_, attribute| !altered.contains(&attribute.id));
+        let mut cache_write = self.cache_by_id.write().await;
+        cache_write.retain(|attribute_id, _| !altered.contains(attribute_id));
     }
 }
 
@@ -50,6 +173,8 @@ pub enum ResolveError<S> {
     StorageError(#[from] S),
     #[error("ident `{0}` not found")]
     IdentNotFound(String),
+    #[error("attribute {0} not found")]
+    IdNotFound(u64),
 }
 
 fn resolve_by_ident<'a, S: ReadStorage<'a>>(
@@ -64,9 +189,53 @@ fn resolve_by_ident<'a, S: ReadStorage<'a>>(
     if let Some(datom) = storage.find(restricts).next() {
         return resolve_by_id(storage, datom?.entity, tx);
     }
+    // Fall back to previous idents of renamed attributes.
+    // [?attribute :db/attr/alias ?ident]
+    let restricts = Restricts::new(tx)
+        .with_attribute(DB_ATTR_ALIAS_ID)
+        .with_value(Value::Str(ident.to_string()));
+    if let Some(datom) = storage.find(restricts).next() {
+        return resolve_by_id(storage, datom?.entity, tx);
+    }
     Ok(None)
 }
 
+fn resolve_entity_by_ident<'a, S: ReadStorage<'a>>(
+    storage: &'a S,
+    ident: &str,
+    tx: u64,
+) -> Result<Option<IdentifiedEntity>, S::Error> {
+    // [?entity :db/ident ident]
+    let restricts = Restricts::new(tx)
+        .with_attribute(DB_IDENT_ID)
+        .with_value(Value::Keyword(ident.to_string()));
+    let datom = storage.find(restricts).next().transpose()?;
+    Ok(datom.map(|datom| IdentifiedEntity {
+        id: datom.entity,
+        version: datom.tx,
+    }))
+}
+
+fn is_schema_attribute(attribute_id: u64) -> bool {
+    matches!(
+        attribute_id,
+        DB_ATTR_IDENT_ID
+            | DB_ATTR_CARDINALITY_ID
+            | DB_ATTR_TYPE_ID
+            | DB_ATTR_DOC_ID
+            | DB_ATTR_UNIQUE_ID
+            | DB_ATTR_COMPONENT_ID
+            | DB_ATTR_VERIFY_REFS_ID
+            | DB_ATTR_ALIAS_ID
+            | DB_ATTR_TUPLE_TYPE_ID
+            | DB_ATTR_TUPLE_TYPES_ID
+            | DB_ATTR_TUPLE_ATTRS_ID
+            | DB_ATTR_INDEX_ID
+            | DB_ATTR_NO_HISTORY_ID
+            | DB_ATTR_PREDS_ID
+    )
+}
+
 fn resolve_by_id<'a, S: ReadStorage<'a>>(
     storage: &'a S,
     attribute_id: u64,
@@ -75,9 +244,13 @@ fn resolve_by_id<'a, S: ReadStorage<'a>>(
     let mut builder = AttributeBuilder::new(attribute_id);
     // [?attribute _ _]
     let restricts = Restricts::new(tx).with_entity(attribute_id);
-    for datom in storage.find(restricts) {
+    for datom in storage.find(restricts.clone()) {
         builder.consume(datom?);
     }
+    // Retractions alter the attribute too, e.g. removing its uniqueness.
+    for datom in storage.find(restricts.with_history()) {
+        builder.altered_at(datom?.tx);
+    }
     match builder.build() {
         Some(attribute) => Ok(Some(Arc::new(attribute))),
         None => Ok(None),
@@ -90,10 +263,14 @@ mod tests {
 
     use crate::clock::Instant;
     use crate::schema::default::default_datoms;
+    use crate::schema::*;
     use crate::storage::attribute_resolver::*;
     use crate::storage::memory::*;
     use crate::storage::*;
+    use crate::tx::function::TransactionFunctions;
     use crate::tx::transactor;
+    use crate::tx::transactor::TransactorConfig;
+    use crate::tx::EntityOperation;
     use crate::tx::Transaction;
 
     struct CountingStorage {
@@ -133,6 +310,14 @@ mod tests {
         fn save(&mut self, datoms: &[Datom]) -> Result<(), Self::Error> {
             self.inner.save(datoms)
         }
+
+        fn purge_history(
+            &mut self,
+            attributes: &[u64],
+            before_tx: u64,
+        ) -> Result<usize, Self::Error> {
+            self.inner.purge_history(attributes, before_tx)
+        }
     }
 
     fn create_storage() -> CountingStorage {
@@ -156,7 +341,15 @@ mod tests {
         let resolver = AttributeResolver::new();
         let attribute = AttributeDefinition::new("foo/bar", ValueType::U64);
         let transaction = Transaction::new().with(attribute);
-        let tx_result = transactor::transact(&storage, &resolver, Instant(0), transaction).await;
+        let tx_result = transactor::transact(
+            &storage,
+            &resolver,
+            &TransactionFunctions::new(),
+            &TransactorConfig::default(),
+            Instant(0),
+            transaction,
+        )
+        .await;
         assert!(tx_result.is_ok());
         assert!(storage.save(&tx_result.unwrap().tx_data).is_ok());
 
@@ -178,7 +371,15 @@ mod tests {
         let resolver = AttributeResolver::new();
         let attribute = AttributeDefinition::new("foo/bar", ValueType::U64);
         let transaction = Transaction::new().with(attribute);
-        let tx_result = transactor::transact(&storage, &resolver, Instant(0), transaction).await;
+        let tx_result = transactor::transact(
+            &storage,
+            &resolver,
+            &TransactionFunctions::new(),
+            &TransactorConfig::default(),
+            Instant(0),
+            transaction,
+        )
+        .await;
         assert!(tx_result.is_ok());
         assert!(storage.save(&tx_result.unwrap().tx_data).is_ok());
 
@@ -196,4 +397,170 @@ mod tests {
         // No additional calls to storage were needed to resolve cached attribute.
         assert_eq!(queries, storage.current_count());
     }
+
+    #[tokio::test]
+    async fn resolves_existing_attribute_by_id() {
+        let storage = create_storage();
+        let resolver = AttributeResolver::new();
+
+        let result = resolver
+            .resolve_id(&storage, DB_ATTR_DOC_ID, u64::MAX)
+            .await;
+
+        assert!(result.is_ok_and(|attribute| attribute.definition.ident == DB_ATTR_DOC_IDENT));
+    }
+
+    #[tokio::test]
+    async fn returns_none_when_attribute_id_does_not_exist() {
+        let storage = create_storage();
+        let resolver = AttributeResolver::new();
+        let result = resolver.resolve_id(&storage, 42, u64::MAX).await;
+        assert!(result.is_err_and(|err| matches!(err, ResolveError::IdNotFound(42))));
+    }
+
+    #[tokio::test]
+    async fn cache_hit_by_id() {
+        let storage = create_storage();
+        let resolver = AttributeResolver::new();
+
+        let result1 = resolver
+            .resolve_id(&storage, DB_ATTR_DOC_ID, u64::MAX)
+            .await;
+        assert!(result1.is_ok());
+        let queries = storage.current_count();
+        assert!(queries > 0);
+
+        let result2 = resolver
+            .resolve_id(&storage, DB_ATTR_DOC_ID, u64::MAX)
+            .await;
+        assert!(result2.is_ok());
+
+        // No additional calls to storage were needed to resolve cached attribute.
+        assert_eq!(queries, storage.current_count());
+    }
+
+    async fn transact(
+        storage: &mut CountingStorage,
+        resolver: &AttributeResolver,
+        transaction: Transaction,
+    ) -> u64 {
+        let tx_result = transactor::transact(
+            &*storage,
+            resolver,
+            &TransactionFunctions::new(),
+            &TransactorConfig::default(),
+            Instant(0),
+            transaction,
+        )
+        .await
+        .expect("Unable to transact");
+        storage
+            .save(&tx_result.tx_data)
+            .expect("Unable to save datoms");
+        resolver.invalidate(&tx_result.tx_data).await;
+        tx_result.tx_id
+    }
+
+    #[tokio::test]
+    async fn resolve_altered_attribute() {
+        let mut storage = create_storage();
+        let resolver = AttributeResolver::new();
+        let attribute = AttributeDefinition::new("foo/bar", ValueType::U64);
+        let tx1 = transact(&mut storage, &resolver, Transaction::new().with(attribute)).await;
+        assert!(resolver
+            .resolve(&storage, "foo/bar", u64::MAX)
+            .await
+            .is_ok());
+
+        let alteration = AttributeAlteration::new("foo/bar").many();
+        let tx2 = transact(&mut storage, &resolver, Transaction::new().with(alteration)).await;
+
+        let latest = resolver.resolve(&storage, "foo/bar", u64::MAX).await;
+        assert!(latest.is_ok_and(|attribute| attribute.version == tx2
+            && attribute.definition.cardinality == Cardinality::Many));
+        let previous = resolver.resolve(&storage, "foo/bar", tx1).await;
+        assert!(previous.is_ok_and(|attribute| attribute.version == tx1
+            && attribute.definition.cardinality == Cardinality::One));
+    }
+
+    #[tokio::test]
+    async fn resolve_attribute_before_retraction_only_alteration() {
+        let mut storage = create_storage();
+        let resolver = AttributeResolver::new();
+        let attribute = AttributeDefinition::new("foo/bar", ValueType::U64).unique();
+        let tx1 = transact(&mut storage, &resolver, Transaction::new().with(attribute)).await;
+
+        let alteration = AttributeAlteration::new("foo/bar").not_unique();
+        let tx2 = transact(&mut storage, &resolver, Transaction::new().with(alteration)).await;
+
+        let latest = resolver.resolve(&storage, "foo/bar", u64::MAX).await;
+        assert!(latest.is_ok_and(
+            |attribute| attribute.version == tx2 && attribute.definition.unique.is_none()
+        ));
+        let previous = resolver.resolve(&storage, "foo/bar", tx1).await;
+        assert!(previous.is_ok_and(
+            |attribute| attribute.version == tx1 && attribute.definition.unique.is_some()
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolve_renamed_attribute_by_previous_ident() {
+        let mut storage = create_storage();
+        let resolver = AttributeResolver::new();
+        let attribute = AttributeDefinition::new("foo/bar", ValueType::U64);
+        transact(&mut storage, &resolver, Transaction::new().with(attribute)).await;
+        let original = resolver.resolve(&storage, "foo/bar", u64::MAX).await;
+
+        let alteration = AttributeAlteration::new("foo/bar").rename("foo/baz");
+        transact(&mut storage, &resolver, Transaction::new().with(alteration)).await;
+
+        let renamed = resolver.resolve(&storage, "foo/baz", u64::MAX).await;
+        let previous = resolver.resolve(&storage, "foo/bar", u64::MAX).await;
+        let id = original.unwrap().id;
+        assert!(renamed.is_ok_and(|attribute| attribute.id == id));
+        assert!(previous
+            .is_ok_and(|attribute| attribute.id == id && attribute.definition.ident == "foo/baz"));
+    }
+
+    #[tokio::test]
+    async fn attribute_not_found_before_creation() {
+        let mut storage = create_storage();
+        let resolver = AttributeResolver::new();
+        let attribute = AttributeDefinition::new("foo/bar", ValueType::U64);
+        let tx = transact(&mut storage, &resolver, Transaction::new().with(attribute)).await;
+
+        assert!(resolver.resolve(&storage, "foo/bar", tx).await.is_ok());
+        let result = resolver.resolve(&storage, "foo/bar", tx - 1).await;
+        assert!(result.is_err_and(|err| matches!(err, ResolveError::IdentNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn resolve_entity_by_ident() {
+        let mut storage = create_storage();
+        let resolver = AttributeResolver::new();
+        let transaction = Transaction::new().with(
+            EntityOperation::on_temp_id("active")
+                .assert(DB_IDENT_IDENT, Value::keyword("status/active")),
+        );
+        let tx = transact(&mut storage, &resolver, transaction).await;
+
+        let result1 = resolver
+            .resolve_entity(&storage, "status/active", u64::MAX)
+            .await;
+        assert!(result1.is_ok());
+        let queries = storage.current_count();
+
+        let result2 = resolver
+            .resolve_entity(&storage, "status/active", u64::MAX)
+            .await;
+        assert_eq!(result1.ok(), result2.ok());
+
+        // No additional calls to storage were needed to resolve cached entity.
+        assert_eq!(queries, storage.current_count());
+
+        let result = resolver
+            .resolve_entity(&storage, "status/active", tx - 1)
+            .await;
+        assert!(result.is_err_and(|err| matches!(err, ResolveError::IdentNotFound(_))));
+    }
 }
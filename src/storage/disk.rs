@@ -1,9 +1,13 @@
 use std::marker::PhantomData;
 use std::path::Path;
+use std::sync::Arc;
 
 use rocksdb::*;
 use thiserror::Error;
 
+use crate::schema::partition::counter;
+use crate::storage::flags::AttributeFlags;
+use crate::storage::history::*;
 use crate::storage::iter::*;
 use crate::storage::restricts::*;
 use crate::storage::serde::index::*;
@@ -14,16 +18,20 @@ pub struct ReadOnly;
 pub struct ReadWrite;
 
 pub struct DiskStorage<Mode> {
-    db: rocksdb::DB,
+    db: Arc<rocksdb::DB>,
+    flags: AttributeFlags,
     marker: PhantomData<Mode>,
 }
 
 impl<Mode> DiskStorage<Mode> {
-    fn new(db: rocksdb::DB) -> Self {
-        Self {
-            db,
+    fn new(db: rocksdb::DB) -> Result<Self, DiskStorageError> {
+        let mut storage = Self {
+            db: Arc::new(db),
+            flags: AttributeFlags::new(),
             marker: PhantomData,
-        }
+        };
+        storage.flags = AttributeFlags::load(&storage)?;
+        Ok(storage)
     }
 }
 
@@ -37,6 +45,7 @@ impl Partition for Index {
             Self::Eavt => "eavt",
             Self::Aevt => "aevt",
             Self::Avet => "avet",
+            Self::Vaet => "vaet",
         }
     }
 }
@@ -52,11 +61,12 @@ impl Partition for System {
     }
 }
 
-fn partitions() -> [&'static str; 4] {
+fn partitions() -> [&'static str; 5] {
     [
         Index::Eavt.name(),
         Index::Aevt.name(),
         Index::Avet.name(),
+        Index::Vaet.name(),
         System.name(),
     ]
 }
@@ -75,7 +85,7 @@ impl DiskStorage<ReadOnly> {
         options.create_if_missing(true);
         options.create_missing_column_families(true);
         let db = DB::open_cf_for_read_only(&options, path, partitions(), false)?;
-        Ok(Self::new(db))
+        Self::new(db)
     }
 }
 
@@ -85,7 +95,7 @@ impl DiskStorage<ReadWrite> {
         options.create_if_missing(true);
         options.create_missing_column_families(true);
         let db = DB::open_cf(&options, path, partitions())?;
-        Ok(Self::new(db))
+        Self::new(db)
     }
 }
 
@@ -96,17 +106,120 @@ impl WriteStorage for DiskStorage<ReadWrite> {
         let eavt = cf_handle(&self.db, Index::Eavt)?;
         let aevt = cf_handle(&self.db, Index::Aevt)?;
         let avet = cf_handle(&self.db, Index::Avet)?;
+        let vaet = cf_handle(&self.db, Index::Vaet)?;
         let system = cf_handle(&self.db, System)?;
+        let mut flags = self.flags.clone();
+        let became_indexed = flags.update(datoms);
+        let removed = Removed::new(datoms, &flags);
         let mut batch = rocksdb::WriteBatch::default();
+        for prefix in &removed.superseded {
+            self.delete_prefix(&mut batch, prefix, |_| true)?;
+        }
+        for excised in &removed.excised {
+            let prefix = datom::serialize::eavt_entity_prefix(excised.entity);
+            self.delete_prefix(&mut batch, &prefix, |datom| excised.contains(datom))?;
+        }
         let mut latest_entity_id = 0;
         for datom in datoms {
+            latest_entity_id = latest_entity_id.max(counter(datom.entity));
+            if removed.contains(datom) {
+                continue;
+            }
             batch.put_cf(eavt, datom::serialize::eavt(datom), "");
             batch.put_cf(aevt, datom::serialize::aevt(datom), "");
-            batch.put_cf(avet, datom::serialize::avet(datom), "");
-            latest_entity_id = latest_entity_id.max(datom.entity);
+            if flags.is_indexed(datom.attribute) {
+                batch.put_cf(avet, datom::serialize::avet(datom), "");
+            }
+            if let Value::Ref(_) = datom.value {
+                batch.put_cf(vaet, datom::serialize::vaet(datom), "");
+            }
+        }
+        for attribute in became_indexed {
+            // Copy all datoms of the attribute, including retracted ones, from AEVT to AVET.
+            let mut iterator = self.db.raw_iterator_cf(aevt);
+            iterator.seek(datom::serialize::aevt_prefix(attribute));
+            while let Some(bytes) = iterator.key() {
+                let datom = datom::deserialize(Index::Aevt, bytes)?;
+                if datom.attribute != attribute {
+                    break;
+                }
+                batch.put_cf(avet, datom::serialize::avet(&datom), "");
+                iterator.next();
+            }
+            iterator.status()?;
         }
         batch.put_cf(system, KEY_LATEST_ENTITY_ID, latest_entity_id.to_be_bytes());
         self.db.write(batch)?;
+        self.flags = flags;
+        Ok(())
+    }
+
+    fn purge_history(&mut self, attributes: &[u64], before_tx: u64) -> Result<usize, Self::Error> {
+        let aevt = cf_handle(&self.db, Index::Aevt)?;
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut purged = 0;
+        for &attribute in attributes {
+            let mut purge = HistoryPurge::new(before_tx);
+            let mut iterator = self.db.raw_iterator_cf(aevt);
+            iterator.seek(datom::serialize::aevt_prefix(attribute));
+            while let Some(bytes) = iterator.key() {
+                let datom = datom::deserialize(Index::Aevt, bytes)?;
+                if datom.attribute != attribute {
+                    break;
+                }
+                if purge.is_purged(&datom) {
+                    self.delete(&mut batch, &datom)?;
+                    purged += 1;
+                }
+                iterator.next();
+            }
+            iterator.status()?;
+        }
+        self.db.write(batch)?;
+        Ok(purged)
+    }
+}
+
+impl DiskStorage<ReadWrite> {
+    /// Adds the removal of all datoms whose EAVT keys start with `prefix`, and are selected by
+    /// `filter`, to `batch`.
+    fn delete_prefix(
+        &self,
+        batch: &mut WriteBatch,
+        prefix: &[u8],
+        filter: impl Fn(&Datom) -> bool,
+    ) -> Result<(), DiskStorageError> {
+        let mut iterator = self.db.raw_iterator_cf(cf_handle(&self.db, Index::Eavt)?);
+        iterator.seek(prefix);
+        while let Some(bytes) = iterator.key().filter(|bytes| bytes.starts_with(prefix)) {
+            let datom = datom::deserialize(Index::Eavt, bytes)?;
+            if filter(&datom) {
+                self.delete(batch, &datom)?;
+            }
+            iterator.next();
+        }
+        iterator.status()?;
+        Ok(())
+    }
+
+    /// Adds the removal of `datom` from all indexes to `batch`.
+    fn delete(&self, batch: &mut WriteBatch, datom: &Datom) -> Result<(), DiskStorageError> {
+        batch.delete_cf(
+            cf_handle(&self.db, Index::Eavt)?,
+            datom::serialize::eavt(datom),
+        );
+        batch.delete_cf(
+            cf_handle(&self.db, Index::Aevt)?,
+            datom::serialize::aevt(datom),
+        );
+        batch.delete_cf(
+            cf_handle(&self.db, Index::Avet)?,
+            datom::serialize::avet(datom),
+        );
+        batch.delete_cf(
+            cf_handle(&self.db, Index::Vaet)?,
+            datom::serialize::vaet(datom),
+        );
         Ok(())
     }
 }
@@ -116,37 +229,84 @@ impl<'a, Mode> ReadStorage<'a> for DiskStorage<Mode> {
     type Iter = DatomsIterator<DiskStorageIter<'a>>;
 
     fn find(&'a self, restricts: Restricts) -> Self::Iter {
-        let range = RestrictedIndexRange::from(restricts);
-        let iter = DiskStorageIter::new(&range, &self.db);
+        let range = RestrictedIndexRange::new(restricts, &self.flags);
+        let cf = cf_handle(&self.db, range.index).unwrap(); // TODO
+        let iter = DiskStorageIter::new(&range, self.db.raw_iterator_cf(cf));
         DatomsIterator::new(iter, range)
     }
 
     fn latest_entity_id(&self) -> Result<u64, Self::Error> {
-        self.try_latest_entity_id()
+        let system = cf_handle(&self.db, System)?;
+        let bytes = self.db.get_cf(system, KEY_LATEST_ENTITY_ID)?;
+        Ok(parse_entity_id(bytes))
     }
 }
 
-impl<Mode> DiskStorage<Mode> {
-    fn try_latest_entity_id(&self) -> Result<u64, DiskStorageError> {
+impl<Mode> SnapshotStorage for DiskStorage<Mode> {
+    type Snapshot = DiskSnapshot;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        let snapshot = self.db.snapshot();
+        // SAFETY: The snapshot borrows the DB, which `DiskSnapshot` keeps alive for as long as
+        // the snapshot. Iterators created from the snapshot borrow the `DiskSnapshot` itself.
+        let snapshot = unsafe {
+            std::mem::transmute::<
+                SnapshotWithThreadMode<'_, rocksdb::DB>,
+                SnapshotWithThreadMode<'static, rocksdb::DB>,
+            >(snapshot)
+        };
+        DiskSnapshot {
+            snapshot,
+            db: self.db.clone(),
+            flags: self.flags.clone(),
+        }
+    }
+}
+
+/// A point in time view of a `DiskStorage`, backed by a RocksDB snapshot which is released when
+/// this is dropped.
+pub struct DiskSnapshot {
+    // Declared before `db`, such that it's released before the DB is dropped.
+    snapshot: SnapshotWithThreadMode<'static, rocksdb::DB>,
+    db: Arc<rocksdb::DB>,
+    flags: AttributeFlags,
+}
+
+impl<'a> ReadStorage<'a> for DiskSnapshot {
+    type Error = DiskStorageError;
+    type Iter = DatomsIterator<DiskStorageIter<'a>>;
+
+    fn find(&'a self, restricts: Restricts) -> Self::Iter {
+        let range = RestrictedIndexRange::new(restricts, &self.flags);
+        let cf = cf_handle(&self.db, range.index).unwrap(); // TODO
+        let iter = DiskStorageIter::new(&range, self.snapshot.raw_iterator_cf(cf));
+        DatomsIterator::new(iter, range)
+    }
+
+    fn latest_entity_id(&self) -> Result<u64, Self::Error> {
         let system = cf_handle(&self.db, System)?;
-        let bytes = self.db.get_cf(system, KEY_LATEST_ENTITY_ID)?;
-        let id = bytes
-            .and_then(|bytes| bytes.try_into().ok())
-            .map(u64::from_be_bytes)
-            .unwrap_or(0);
-        Ok(id)
+        let bytes = self.snapshot.get_cf(system, KEY_LATEST_ENTITY_ID)?;
+        Ok(parse_entity_id(bytes))
     }
 }
 
+fn parse_entity_id(bytes: Option<Vec<u8>>) -> u64 {
+    bytes
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_be_bytes)
+        .unwrap_or(0)
+}
+
 pub struct DiskStorageIter<'a> {
-    iterator: DBRawIteratorWithThreadMode<'a, DBWithThreadMode<SingleThreaded>>,
+    iterator: DBRawIteratorWithThreadMode<'a, rocksdb::DB>,
     should_continue: bool,
 }
 
 impl<'a> DiskStorageIter<'a> {
-    fn new(range: &RestrictedIndexRange, db: &'a rocksdb::DB) -> Self {
-        let cf = cf_handle(db, range.index).unwrap(); // TODO
-        let mut iterator = db.raw_iterator_cf(cf);
+    fn new(
+        range: &RestrictedIndexRange,
+        mut iterator: DBRawIteratorWithThreadMode<'a, rocksdb::DB>,
+    ) -> Self {
         match &range.start {
             None => iterator.seek_to_first(),
             Some(start) => iterator.seek(start),
@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+
+use crate::datom::*;
+use crate::schema::*;
+use crate::storage::restricts::Restricts;
+use crate::storage::ReadStorage;
+
+/// Tracks the schema flags which affect how datoms are stored: which attributes are kept in the
+/// AVET index (attributes with `db/attr/index` or `db/attr/unique`), and which don't keep their
+/// history (attributes with `db/attr/noHistory`). Storages derive it from the schema datoms they
+/// save, so that they know which datoms to write and which value lookups can use AVET.
+#[derive(Debug, Default, Clone)]
+pub struct AttributeFlags {
+    index: HashSet<u64>,
+    unique: HashSet<u64>,
+    no_history: HashSet<u64>,
+}
+
+impl AttributeFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the flags of a storage which was saved to previously.
+    ///
+    /// # Errors
+    /// Storage related errors
+    pub fn load<'a, S: ReadStorage<'a>>(storage: &'a S) -> Result<Self, S::Error> {
+        let mut flags = Self::new();
+        for attribute in [DB_ATTR_INDEX_ID, DB_ATTR_UNIQUE_ID, DB_ATTR_NO_HISTORY_ID] {
+            let restricts = Restricts::new(u64::MAX).with_attribute(attribute);
+            let datoms = storage.find(restricts).collect::<Result<Vec<_>, _>>()?;
+            flags.update(&datoms);
+        }
+        Ok(flags)
+    }
+
+    pub fn is_indexed(&self, attribute: u64) -> bool {
+        self.index.contains(&attribute) || self.unique.contains(&attribute)
+    }
+
+    pub fn keeps_history(&self, attribute: u64) -> bool {
+        !self.no_history.contains(&attribute)
+    }
+
+    /// Applies the schema changes of `datoms`, and returns the attributes which became indexed,
+    /// whose existing datoms should be backfilled to AVET.
+    pub fn update(&mut self, datoms: &[Datom]) -> Vec<u64> {
+        let indexed_before = datoms
+            .iter()
+            .filter(|datom| is_index_attribute(datom.attribute))
+            .map(|datom| (datom.entity, self.is_indexed(datom.entity)))
+            .collect::<Vec<_>>();
+        // A transaction replacing a value retracts the old one after asserting the new one.
+        for op in [Op::Retract, Op::Assert] {
+            for datom in datoms.iter().filter(|datom| datom.op == op) {
+                let attributes = match datom.attribute {
+                    DB_ATTR_INDEX_ID => &mut self.index,
+                    DB_ATTR_UNIQUE_ID => &mut self.unique,
+                    DB_ATTR_NO_HISTORY_ID => &mut self.no_history,
+                    _ => continue,
+                };
+                match (op, &datom.value) {
+                    (Op::Assert, Value::U64(0)) | (Op::Retract, _) => {
+                        attributes.remove(&datom.entity)
+                    }
+                    (Op::Assert, _) => attributes.insert(datom.entity),
+                };
+            }
+        }
+        let mut became_indexed = indexed_before
+            .into_iter()
+            .filter(|&(attribute, before)| !before && self.is_indexed(attribute))
+            .map(|(attribute, _)| attribute)
+            .collect::<Vec<_>>();
+        became_indexed.sort_unstable();
+        became_indexed.dedup();
+        became_indexed
+    }
+}
+
+fn is_index_attribute(attribute: u64) -> bool {
+    matches!(attribute, DB_ATTR_INDEX_ID | DB_ATTR_UNIQUE_ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::attribute::Unique;
+
+    #[test]
+    fn index_unique_and_indexed_attributes() {
+        let mut flags = AttributeFlags::new();
+
+        let became_indexed = flags.update(&[
+            Datom::add(100, DB_ATTR_UNIQUE_ID, Unique::Value as u64, 1),
+            Datom::add(101, DB_ATTR_INDEX_ID, 1u64, 1),
+            Datom::add(102, DB_ATTR_IDENT_ID, "foo/bar", 1),
+        ]);
+
+        assert_eq!(vec![100, 101], became_indexed);
+        assert!(flags.is_indexed(100));
+        assert!(flags.is_indexed(101));
+        assert!(!flags.is_indexed(102));
+    }
+
+    #[test]
+    fn keep_indexing_when_uniqueness_changes() {
+        let mut flags = AttributeFlags::new();
+        flags.update(&[Datom::add(100, DB_ATTR_UNIQUE_ID, Unique::Value as u64, 1)]);
+
+        let became_indexed = flags.update(&[
+            Datom::add(100, DB_ATTR_UNIQUE_ID, Unique::Identity as u64, 2),
+            Datom::retract(100, DB_ATTR_UNIQUE_ID, Unique::Value as u64, 2),
+        ]);
+
+        assert!(became_indexed.is_empty());
+        assert!(flags.is_indexed(100));
+    }
+
+    #[test]
+    fn stop_indexing_when_retracted() {
+        let mut flags = AttributeFlags::new();
+        flags.update(&[Datom::add(100, DB_ATTR_INDEX_ID, 1u64, 1)]);
+
+        flags.update(&[Datom::retract(100, DB_ATTR_INDEX_ID, 1u64, 2)]);
+
+        assert!(!flags.is_indexed(100));
+    }
+
+    #[test]
+    fn drop_history_of_flagged_attributes() {
+        let mut flags = AttributeFlags::new();
+
+        let became_indexed = flags.update(&[Datom::add(100, DB_ATTR_NO_HISTORY_ID, 1u64, 1)]);
+
+        assert!(became_indexed.is_empty());
+        assert!(!flags.keeps_history(100));
+        assert!(flags.keeps_history(101));
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+
+use crate::datom::*;
+use crate::schema::*;
+use crate::storage::flags::AttributeFlags;
+use crate::storage::serde::datom;
+
+/// Datoms which are physically removed from storage when saving a batch of datoms, rather than
+/// kept as history.
+pub struct Removed {
+    /// EAVT prefixes of the [e a v] combinations which the batch retracts from attributes that
+    /// don't keep their history. All datoms with these prefixes are superseded.
+    pub superseded: BTreeSet<Vec<u8>>,
+    pub excised: Vec<Excised>,
+}
+
+impl Removed {
+    pub fn new(datoms: &[Datom], flags: &AttributeFlags) -> Self {
+        let superseded = datoms
+            .iter()
+            .filter(|datom| datom.op == Op::Retract && !flags.keeps_history(datom.attribute))
+            .map(datom::serialize::eavt_prefix)
+            .collect();
+        Self {
+            superseded,
+            excised: excised(datoms),
+        }
+    }
+
+    /// Returns true if `datom` of the batch should not be saved either.
+    pub fn contains(&self, datom: &Datom) -> bool {
+        self.excised.iter().any(|excised| excised.contains(datom))
+            || (!self.superseded.is_empty()
+                && self
+                    .superseded
+                    .contains(&datom::serialize::eavt_prefix(datom)))
+    }
+}
+
+/// Datoms removed by an excision: all datoms of `entity`, or only its values of `attributes` if
+/// there are any. Includes retracted datoms, such that no point in time can resurface them.
+#[derive(Debug, PartialEq)]
+pub struct Excised {
+    pub entity: u64,
+    pub attributes: Vec<u64>,
+}
+
+impl Excised {
+    pub fn contains(&self, datom: &Datom) -> bool {
+        datom.entity == self.entity
+            && (self.attributes.is_empty() || self.attributes.contains(&datom.attribute))
+    }
+}
+
+/// Returns the excisions recorded by `datoms`, see `Transaction::excise`.
+fn excised(datoms: &[Datom]) -> Vec<Excised> {
+    datoms
+        .iter()
+        .filter_map(|datom| match datom {
+            Datom {
+                entity: excision,
+                attribute: DB_EXCISE_ID,
+                value: Value::Ref(entity),
+                op: Op::Assert,
+                ..
+            } => Some(Excised {
+                entity: *entity,
+                attributes: datoms
+                    .iter()
+                    .filter(|datom| datom.entity == *excision)
+                    .filter(|datom| datom.attribute == DB_EXCISE_ATTRS_ID)
+                    .filter_map(|datom| match datom.value {
+                        Value::Ref(attribute) => Some(attribute),
+                        _ => None,
+                    })
+                    .collect(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Selects the datoms to purge from the history of an attribute: values which were retracted
+/// before `before_tx`, along with their retractions. Values which are current at `before_tx` are
+/// never purged. Datoms of the attribute must be fed in AEVT order, including retracted ones.
+pub struct HistoryPurge {
+    before_tx: u64,
+    current: Option<(u64, Value)>, // Entity and value of the datoms seen last
+    purging: bool,
+}
+
+impl HistoryPurge {
+    pub fn new(before_tx: u64) -> Self {
+        Self {
+            before_tx,
+            current: None,
+            purging: false,
+        }
+    }
+
+    pub fn is_purged(&mut self, datom: &Datom) -> bool {
+        let same_value = matches!(
+            &self.current,
+            Some((entity, value)) if *entity == datom.entity && *value == datom.value
+        );
+        if !same_value {
+            self.current = Some((datom.entity, datom.value.clone()));
+            self.purging = false;
+        }
+        // Datoms of the same [e a v] are sorted from latest to earliest, so once a retraction
+        // before `before_tx` is found, all the remaining datoms are superseded by it.
+        if datom.op == Op::Retract && datom.tx < self.before_tx {
+            self.purging = true;
+        }
+        self.purging
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excise_entity() {
+        let datoms = [
+            Datom::add(1000, DB_TX_TIME_ID, 0u64, 1000),
+            Datom::add(200, DB_EXCISE_ID, Value::Ref(100), 1000),
+            Datom::add(201, DB_EXCISE_ID, Value::Ref(101), 1000),
+            Datom::add(201, DB_EXCISE_ATTRS_ID, Value::Ref(10), 1000),
+            Datom::add(201, DB_EXCISE_ATTRS_ID, Value::Ref(11), 1000),
+        ];
+
+        let excised = excised(&datoms);
+
+        assert_eq!(
+            vec![
+                Excised {
+                    entity: 100,
+                    attributes: vec![],
+                },
+                Excised {
+                    entity: 101,
+                    attributes: vec![10, 11],
+                },
+            ],
+            excised
+        );
+        assert!(excised[0].contains(&Datom::add(100, 12, 1u64, 999)));
+        assert!(excised[1].contains(&Datom::retract(101, 10, 1u64, 999)));
+        assert!(!excised[1].contains(&Datom::add(101, 12, 1u64, 999)));
+        assert!(!excised[1].contains(&Datom::add(102, 10, 1u64, 999)));
+    }
+
+    #[test]
+    fn purge_values_retracted_before_tx() {
+        let mut purge = HistoryPurge::new(1003);
+
+        let datoms = [
+            Datom::add(100, 101, 3u64, 1002),
+            Datom::retract(100, 101, 2u64, 1002),
+            Datom::add(100, 101, 2u64, 1001),
+            Datom::retract(100, 101, 1u64, 1001),
+            Datom::add(100, 101, 1u64, 1000),
+            Datom::retract(102, 101, 1u64, 1003),
+            Datom::add(102, 101, 1u64, 1000),
+        ];
+        let purged: Vec<_> = datoms
+            .iter()
+            .filter(|datom| purge.is_purged(datom))
+            .cloned()
+            .collect();
+
+        assert_eq!(datoms[1..5].to_vec(), purged);
+    }
+
+    #[test]
+    fn keep_values_asserted_again() {
+        let mut purge = HistoryPurge::new(1003);
+
+        let datoms = [
+            Datom::add(100, 101, 1u64, 1002),
+            Datom::retract(100, 101, 1u64, 1001),
+            Datom::add(100, 101, 1u64, 1000),
+        ];
+        let purged: Vec<_> = datoms
+            .iter()
+            .filter(|datom| purge.is_purged(datom))
+            .cloned()
+            .collect();
+
+        assert_eq!(datoms[1..].to_vec(), purged);
+    }
+}
@@ -41,7 +41,15 @@ where
             Ok(datom) if self.range.contains(&datom) => Some(Ok(datom)),
             Ok(datom) => {
                 // Datom is out of range, seek to next one
-                if let Some(key) = seek_key(&datom.value, bytes, self.range.tx_value()) {
+                let basis_tx = self.range.tx_value();
+                let key = if datom.tx > basis_tx {
+                    // Datom was added after basis, an earlier datom with the same [e a v] might
+                    // still be in range.
+                    Some(basis_key(&datom.value, bytes, basis_tx))
+                } else {
+                    seek_key(&datom.value, bytes, basis_tx)
+                };
+                if let Some(key) = key {
                     if let Err(err) = self.bytes_iterator.seek(key) {
                         return Some(Err(err));
                     }
@@ -61,6 +69,14 @@ fn seek_key(value: &Value, datom_bytes: &[u8], basis_tx: u64) -> Option<Vec<u8>>
     Some(key)
 }
 
+/// For bytes of a given datom [e a v _ _], seek to the latest datom in the index with the same
+/// [e a v] combination, which was added at or before `basis_tx`.
+fn basis_key(value: &Value, datom_bytes: &[u8], basis_tx: u64) -> Vec<u8> {
+    let mut key = datom_bytes[..key_size(value)].to_vec();
+    (!basis_tx).write_to(&mut key);
+    key
+}
+
 /// Returns lowest value following largest value with given prefix.
 ///
 /// In other words, computes upper bound for a prefix scan over list of keys
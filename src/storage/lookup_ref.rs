@@ -0,0 +1,41 @@
+use thiserror::Error;
+
+use crate::query::pattern::LookupRef;
+use crate::storage::attribute_resolver::*;
+use crate::storage::restricts::Restricts;
+use crate::storage::ReadStorage;
+
+/// Resolves the ID of the entity identified by `lookup_ref`. The attribute of the lookup ref must
+/// be unique, such that at most one entity can have the looked up value.
+pub async fn resolve_lookup_ref<'a, S: ReadStorage<'a>>(
+    storage: &'a S,
+    resolver: &AttributeResolver,
+    lookup_ref: &LookupRef,
+    tx: u64,
+) -> Result<u64, LookupRefError<S::Error>> {
+    let attribute = resolver.resolve(storage, &lookup_ref.attribute, tx).await?;
+    if attribute.definition.unique.is_none() {
+        return Err(LookupRefError::NotUnique(lookup_ref.attribute.clone()));
+    }
+
+    // [?entity attribute value]
+    let restricts = Restricts::new(tx)
+        .with_attribute(attribute.id)
+        .with_value(lookup_ref.value.clone());
+    match storage.find(restricts).next() {
+        Some(datom) => Ok(datom?.entity),
+        None => Err(LookupRefError::NotFound(lookup_ref.clone())),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LookupRefError<S> {
+    #[error("storage error")]
+    StorageError(#[from] S),
+    #[error("resolve error")]
+    ResolveError(#[from] ResolveError<S>),
+    #[error("attribute `{0}` is not unique")]
+    NotUnique(String),
+    #[error("no entity found for lookup ref [{} {:?}]", .0.attribute, .0.value)]
+    NotFound(LookupRef),
+}
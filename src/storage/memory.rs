@@ -1,17 +1,24 @@
 use std::collections::btree_set;
 use std::collections::BTreeSet;
 use std::convert::Infallible;
+use std::sync::Arc;
 
+use crate::schema::partition::counter;
+use crate::storage::flags::AttributeFlags;
+use crate::storage::history::*;
 use crate::storage::iter::*;
 use crate::storage::serde::index::RestrictedIndexRange;
 use crate::storage::serde::*;
 use crate::storage::*;
 
-#[derive(Default)]
+/// Indexes are shared with snapshots, and copied on write while a snapshot is still alive.
+#[derive(Default, Clone)]
 pub struct InMemoryStorage {
-    eavt: BTreeSet<Vec<u8>>,
-    aevt: BTreeSet<Vec<u8>>,
-    avet: BTreeSet<Vec<u8>>,
+    eavt: Arc<BTreeSet<Vec<u8>>>,
+    aevt: Arc<BTreeSet<Vec<u8>>>,
+    avet: Arc<BTreeSet<Vec<u8>>>,
+    vaet: Arc<BTreeSet<Vec<u8>>>,
+    flags: AttributeFlags,
     latest_entity_id: u64,
 }
 
@@ -19,20 +26,99 @@ impl InMemoryStorage {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Copies all datoms of `attribute`, including retracted ones, from AEVT to AVET.
+    fn backfill_avet(&mut self, attribute: u64) {
+        let start = datom::serialize::aevt_prefix(attribute);
+        for bytes in self.aevt.range(start..) {
+            let Ok(datom) = datom::deserialize(Index::Aevt, bytes) else {
+                continue;
+            };
+            if datom.attribute != attribute {
+                break;
+            }
+            Arc::make_mut(&mut self.avet).insert(datom::serialize::avet(&datom));
+        }
+    }
+
+    /// Removes all datoms whose EAVT keys start with `prefix`, and are selected by `filter`, from
+    /// all indexes.
+    fn remove_prefix(&mut self, prefix: &[u8], filter: impl Fn(&Datom) -> bool) {
+        let datoms: Vec<_> = self
+            .eavt
+            .range(prefix.to_vec()..)
+            .take_while(|bytes| bytes.starts_with(prefix))
+            .filter_map(|bytes| datom::deserialize(Index::Eavt, bytes).ok())
+            .filter(filter)
+            .collect();
+        for datom in datoms {
+            self.remove(&datom);
+        }
+    }
+
+    fn remove(&mut self, datom: &Datom) {
+        Arc::make_mut(&mut self.eavt).remove(&datom::serialize::eavt(datom));
+        Arc::make_mut(&mut self.aevt).remove(&datom::serialize::aevt(datom));
+        Arc::make_mut(&mut self.avet).remove(&datom::serialize::avet(datom));
+        Arc::make_mut(&mut self.vaet).remove(&datom::serialize::vaet(datom));
+    }
 }
 
 impl WriteStorage for InMemoryStorage {
     type Error = Infallible;
 
     fn save(&mut self, datoms: &[Datom]) -> Result<(), Self::Error> {
+        let became_indexed = self.flags.update(datoms);
+        let removed = Removed::new(datoms, &self.flags);
+        for prefix in &removed.superseded {
+            self.remove_prefix(prefix, |_| true);
+        }
+        for excised in &removed.excised {
+            let prefix = datom::serialize::eavt_entity_prefix(excised.entity);
+            self.remove_prefix(&prefix, |datom| excised.contains(datom));
+        }
         for datom in datoms {
-            self.latest_entity_id = self.latest_entity_id.max(datom.entity);
-            self.eavt.insert(datom::serialize::eavt(datom));
-            self.aevt.insert(datom::serialize::aevt(datom));
-            self.avet.insert(datom::serialize::avet(datom));
+            self.latest_entity_id = self.latest_entity_id.max(counter(datom.entity));
+            if removed.contains(datom) {
+                continue;
+            }
+            Arc::make_mut(&mut self.eavt).insert(datom::serialize::eavt(datom));
+            Arc::make_mut(&mut self.aevt).insert(datom::serialize::aevt(datom));
+            if self.flags.is_indexed(datom.attribute) {
+                Arc::make_mut(&mut self.avet).insert(datom::serialize::avet(datom));
+            }
+            if let Value::Ref(_) = datom.value {
+                Arc::make_mut(&mut self.vaet).insert(datom::serialize::vaet(datom));
+            }
+        }
+        for attribute in became_indexed {
+            self.backfill_avet(attribute);
         }
         Ok(())
     }
+
+    fn purge_history(&mut self, attributes: &[u64], before_tx: u64) -> Result<usize, Self::Error> {
+        let mut purged = Vec::new();
+        for &attribute in attributes {
+            let mut purge = HistoryPurge::new(before_tx);
+            let start = datom::serialize::aevt_prefix(attribute);
+            for bytes in self.aevt.range(start..) {
+                let Ok(datom) = datom::deserialize(Index::Aevt, bytes) else {
+                    continue;
+                };
+                if datom.attribute != attribute {
+                    break;
+                }
+                if purge.is_purged(&datom) {
+                    purged.push(datom);
+                }
+            }
+        }
+        for datom in &purged {
+            self.remove(datom);
+        }
+        Ok(purged.len())
+    }
 }
 
 impl<'a> ReadStorage<'a> for InMemoryStorage {
@@ -40,7 +126,7 @@ impl<'a> ReadStorage<'a> for InMemoryStorage {
     type Iter = DatomsIterator<InMemoryStorageIter<'a>>;
 
     fn find(&'a self, restricts: Restricts) -> Self::Iter {
-        let range = RestrictedIndexRange::from(restricts);
+        let range = RestrictedIndexRange::new(restricts, &self.flags);
         let iter = InMemoryStorageIter::new(self, &range);
         DatomsIterator::new(iter, range)
     }
@@ -50,6 +136,15 @@ impl<'a> ReadStorage<'a> for InMemoryStorage {
     }
 }
 
+impl SnapshotStorage for InMemoryStorage {
+    // Cloning only shares the indexes, which are copied if the storage is written to later.
+    type Snapshot = InMemoryStorage;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        self.clone()
+    }
+}
+
 pub struct InMemoryStorageIter<'a> {
     index: &'a BTreeSet<Vec<u8>>,
     range: btree_set::Range<'a, Vec<u8>>,
@@ -61,6 +156,7 @@ impl<'a> InMemoryStorageIter<'a> {
             Index::Eavt => &storage.eavt,
             Index::Aevt => &storage.aevt,
             Index::Avet => &storage.avet,
+            Index::Vaet => &storage.vaet,
         };
         let range = match &range.start {
             Some(start) => index.range::<Vec<u8>, _>(start..),
@@ -1,7 +1,10 @@
 pub mod attribute_builder;
 pub mod attribute_resolver;
 pub mod disk;
+pub mod flags;
+pub mod history;
 mod iter;
+pub mod lookup_ref;
 pub mod memory;
 pub mod restricts;
 pub mod serde;
@@ -13,12 +16,14 @@ pub trait ReadStorage<'a> {
     type Error: std::error::Error;
     type Iter: Iterator<Item = Result<Datom, Self::Error>>;
 
-    /// Returns an iterator that yields all *non-retracted* datoms that match the restircts.
+    /// Returns an iterator that yields all *non-retracted* datoms that match the restircts, or
+    /// all datoms including retractions if `Restricts::with_history` is set.
     /// Iterator might fail with `Self::Error` during iteration.
     /// Ordering of datoms is not guaranteed.
     fn find(&'a self, restricts: Restricts) -> Self::Iter;
 
-    /// Returns the latest entity ID stored.
+    /// Returns the latest entity ID stored, by its position in the ID sequence shared by all
+    /// partitions (see `partition::counter`).
     fn latest_entity_id(&self) -> Result<u64, Self::Error>;
 }
 
@@ -26,4 +31,22 @@ pub trait WriteStorage {
     type Error: std::error::Error;
 
     fn save(&mut self, datoms: &[Datom]) -> Result<(), Self::Error>;
+
+    /// Physically removes the history of `attributes` before `before_tx`: values which were
+    /// retracted before it, along with their retractions. Historical queries at earlier
+    /// transactions no longer see the removed values. Returns the number of removed datoms.
+    fn purge_history(&mut self, attributes: &[u64], before_tx: u64) -> Result<usize, Self::Error>;
+}
+
+/// Storage which can pin reads to a single point in time.
+pub trait SnapshotStorage: for<'a> ReadStorage<'a> {
+    /// A read-only view of the storage. All iterators created from the same snapshot observe the
+    /// same datoms, regardless of writes which land while iterating. A snapshot doesn't borrow
+    /// the storage, so the storage can be written to while the snapshot is read.
+    type Snapshot: for<'a> ReadStorage<'a, Error = <Self as ReadStorage<'static>>::Error>
+        + Send
+        + Sync
+        + 'static;
+
+    fn snapshot(&self) -> Self::Snapshot;
 }
@@ -9,6 +9,8 @@ pub struct Restricts {
     pub attribute: Option<u64>,
     pub value: Option<Value>,
     pub tx: TxRestrict,
+    /// Also match retracted datoms and the retractions themselves.
+    pub history: bool,
 }
 
 impl Restricts {
@@ -18,12 +20,13 @@ impl Restricts {
             attribute: None,
             value: None,
             tx: TxRestrict::AtMost(basis_tx),
+            history: false,
         }
     }
 
     pub fn from(clause: &Clause, assignment: &PartialAssignment, basis_tx: u64) -> Self {
         let entity = match clause.entity {
-            Pattern::Constant(entity) => Some(entity),
+            Pattern::Constant(EntityIdentifier::Id(entity)) => Some(entity),
             Pattern::Variable(ref variable) => assignment.get_ref(variable),
             _ => None,
         };
@@ -50,6 +53,7 @@ impl Restricts {
             attribute,
             value,
             tx,
+            history: false,
         }
     }
 
@@ -73,8 +77,13 @@ impl Restricts {
         self
     }
 
+    pub fn with_history(mut self) -> Self {
+        self.history = true;
+        self
+    }
+
     pub fn test(&self, datom: &Datom) -> bool {
-        datom.op == Op::Assert
+        (self.history || datom.op == Op::Assert)
             && self.entity.is_none_or(|e| datom.entity == e)
             && self.attribute.is_none_or(|a| datom.attribute == a)
             && self.value.as_ref().is_none_or(|v| &datom.value == v)
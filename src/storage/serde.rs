@@ -1,3 +1,5 @@
+use ::uuid::Uuid;
+use ordered_float::OrderedFloat;
 use rust_decimal::Decimal;
 use std::fmt::Debug;
 use std::io::Cursor;
@@ -23,6 +25,7 @@ macro_rules! write_to_vec {
 /// | EAVT  | entity / attribute / value / tx | All datoms                     |
 /// | AEVT  | attribute / entity / value / tx | All datoms                     |
 /// | AVET  | attribute / value / entity / tx | Datoms with indexed attributes |
+/// | VAET  | value / attribute / entity / tx | Datoms with reference values   |
 ///
 /// https://docs.datomic.com/pro/query/indexes.html
 #[derive(Debug, Clone, Copy)]
@@ -77,10 +80,23 @@ pub enum Index {
     /// | release/year   | 1967                   | 42 | 1007 | Assert |
     /// | release/year   | 1984                   | 55 | 2367 | Assert |
     Avet,
+
+    /// The VAET index contains all and only datoms whose value is a reference, and provides
+    /// efficient navigation of relationships in reverse. In the example below, all of the
+    /// releases referencing artist 42 are grouped together:
+    ///
+    /// | V  | A               | E  | Tx   | Op     |
+    /// |----|-----------------|----|------|--------|
+    /// | 42 | release/artists | 41 | 1100 | Assert |
+    /// | 42 | release/artists | 43 | 1234 | Assert |
+    /// | 55 | release/artists | 44 | 1234 | Assert |
+    /// | 55 | track/artists   | 70 | 1235 | Assert |
+    Vaet,
 }
 
 pub mod index {
     use super::*;
+    use crate::storage::flags::AttributeFlags;
 
     pub struct RestrictedIndexRange {
         pub restricts: Restricts,
@@ -98,8 +114,11 @@ pub mod index {
         }
     }
 
-    impl From<Restricts> for RestrictedIndexRange {
-        fn from(restricts: Restricts) -> Self {
+    impl RestrictedIndexRange {
+        /// Picks the index to scan for `restricts`. Lookups of references to an entity use VAET.
+        /// Other lookups by attribute and value only use AVET if the attribute is indexed, and
+        /// otherwise scan all values of the attribute in AEVT.
+        pub fn new(restricts: Restricts, flags: &AttributeFlags) -> Self {
             let (index, start) = match &restricts {
                 Restricts {
                     entity: Some(entity),
@@ -120,11 +139,22 @@ pub mod index {
                     entity: Some(entity),
                     ..
                 } => (Index::Eavt, Some(write_to_vec!(entity))),
+                Restricts {
+                    attribute: Some(attribute),
+                    value: Some(value @ Value::Ref(_)),
+                    ..
+                } => (Index::Vaet, Some(write_to_vec!(value, attribute))),
+                Restricts {
+                    value: Some(value @ Value::Ref(_)),
+                    ..
+                } => (Index::Vaet, Some(write_to_vec!(value))),
                 Restricts {
                     attribute: Some(attribute),
                     value: Some(value),
                     ..
-                } => (Index::Avet, Some(write_to_vec!(attribute, value))),
+                } if flags.is_indexed(*attribute) => {
+                    (Index::Avet, Some(write_to_vec!(attribute, value)))
+                }
                 Restricts {
                     attribute: Some(attribute),
                     ..
@@ -175,6 +205,31 @@ pub mod datom {
                 datom.op
             )
         }
+
+        pub fn vaet(datom: &Datom) -> Vec<u8> {
+            write_to_vec!(
+                datom.value,
+                datom.attribute,
+                datom.entity,
+                !datom.tx, // Keep tx in descending order
+                datom.op
+            )
+        }
+
+        /// Prefix of the EAVT keys of all datoms of `entity`.
+        pub fn eavt_entity_prefix(entity: u64) -> Vec<u8> {
+            write_to_vec!(entity)
+        }
+
+        /// Prefix of the EAVT keys of all datoms with the entity, attribute and value of `datom`.
+        pub fn eavt_prefix(datom: &Datom) -> Vec<u8> {
+            write_to_vec!(datom.entity, datom.attribute, datom.value)
+        }
+
+        /// Prefix of the AEVT keys of all datoms of `attribute`.
+        pub fn aevt_prefix(attribute: u64) -> Vec<u8> {
+            write_to_vec!(attribute)
+        }
     }
 
     pub fn deserialize(index: Index, buffer: &[u8]) -> ReadResult<Datom> {
@@ -183,6 +238,7 @@ pub mod datom {
             Index::Eavt => deserialize::eavt(&mut cursor),
             Index::Aevt => deserialize::aevt(&mut cursor),
             Index::Avet => deserialize::avet(&mut cursor),
+            Index::Vaet => deserialize::vaet(&mut cursor),
         }
     }
 
@@ -236,6 +292,22 @@ pub mod datom {
                 op,
             })
         }
+
+        pub fn vaet(cursor: &mut Cursor<&[u8]>) -> ReadResult<Datom> {
+            let value = Value::read_from(cursor)?;
+            let attribute = u64::read_from(cursor)?;
+            let entity = u64::read_from(cursor)?;
+            let tx = !u64::read_from(cursor)?;
+            let op = Op::read_from(cursor)?;
+            // assert!(buffer.is_empty(), "bytes remaining in buffer");
+            Ok(Datom {
+                entity,
+                attribute,
+                value,
+                tx,
+                op,
+            })
+        }
     }
 }
 
@@ -267,6 +339,23 @@ pub trait Writable {
     fn write_to(&self, buffer: &mut Vec<u8>);
 }
 
+/// Longest string or keyword which can be encoded, in bytes.
+pub const MAX_STRING_LENGTH: usize = u16::MAX as usize;
+
+/// Longest big integer which can be encoded, in bytes.
+pub const MAX_BIG_INT_LENGTH: usize = u32::MAX as usize;
+
+/// Returns `false` if `value` contains a string or big integer which is too long for the length
+/// prefix of its encoding, regardless of the total size of the value.
+pub fn is_encodable(value: &Value) -> bool {
+    match value {
+        Value::Str(string) | Value::Keyword(string) => string.len() <= MAX_STRING_LENGTH,
+        Value::BigInt(value) => value.to_signed_bytes_be().len() <= MAX_BIG_INT_LENGTH,
+        Value::Tuple(elements) => elements.iter().all(is_encodable),
+        _ => true,
+    }
+}
+
 impl<const N: usize> Readable for [u8; N] {
     fn read_from(buffer: &mut impl Read) -> ReadResult<Self> {
         let mut bytes = [0; N];
@@ -298,6 +387,7 @@ macro_rules! primitive_impl {
 
 primitive_impl!(u8);
 primitive_impl!(u16);
+primitive_impl!(u32);
 primitive_impl!(u64);
 primitive_impl!(i64);
 
@@ -322,6 +412,199 @@ mod decimal {
     }
 }
 
+mod bool {
+    use super::*;
+
+    impl Readable for bool {
+        fn read_from(buffer: &mut impl Read) -> ReadResult<Self> {
+            match u8::read_from(buffer)? {
+                0 => Ok(false),
+                1 => Ok(true),
+                _ => Err(ReadError::InvalidInput),
+            }
+        }
+    }
+
+    impl Writable for bool {
+        fn size_hint(&self) -> usize {
+            1
+        }
+
+        fn write_to(&self, buffer: &mut Vec<u8>) {
+            u8::from(*self).write_to(buffer);
+        }
+    }
+}
+
+/// Signed integers whose encoding sorts in numeric order: flipping the sign bit moves negative
+/// numbers below positive ones.
+mod instant {
+    use super::*;
+
+    const SIGN_BIT: u64 = 1 << 63;
+
+    pub fn read_from(buffer: &mut impl Read) -> ReadResult<i64> {
+        Ok((u64::read_from(buffer)? ^ SIGN_BIT) as i64)
+    }
+
+    pub fn write_to(value: i64, buffer: &mut Vec<u8>) {
+        (value as u64 ^ SIGN_BIT).write_to(buffer);
+    }
+}
+
+/// Doubles are encoded so that their bytes sort like `OrderedFloat`: positive numbers have their
+/// sign bit flipped, negative numbers have all their bits flipped. Negative zero and NaNs are
+/// normalized first, as `OrderedFloat` considers them equal to zero and to each other.
+mod double {
+    use super::*;
+
+    const SIGN_BIT: u64 = 1 << 63;
+
+    impl Readable for OrderedFloat<f64> {
+        fn read_from(buffer: &mut impl Read) -> ReadResult<Self> {
+            let bits = u64::read_from(buffer)?;
+            let bits = if bits & SIGN_BIT != 0 {
+                bits ^ SIGN_BIT
+            } else {
+                !bits
+            };
+            Ok(OrderedFloat(f64::from_bits(bits)))
+        }
+    }
+
+    impl Writable for OrderedFloat<f64> {
+        fn size_hint(&self) -> usize {
+            size_of::<u64>()
+        }
+
+        fn write_to(&self, buffer: &mut Vec<u8>) {
+            let value = if self.0.is_nan() {
+                f64::NAN
+            } else if self.0 == 0.0 {
+                0.0
+            } else {
+                self.0
+            };
+            let bits = value.to_bits();
+            let bits = if bits & SIGN_BIT == 0 {
+                bits ^ SIGN_BIT
+            } else {
+                !bits
+            };
+            bits.write_to(buffer);
+        }
+    }
+}
+
+mod uuid {
+    use super::*;
+
+    impl Readable for Uuid {
+        fn read_from(buffer: &mut impl Read) -> ReadResult<Self> {
+            let bytes = <[u8; 16]>::read_from(buffer)?;
+            Ok(Self::from_bytes(bytes))
+        }
+    }
+
+    impl Writable for Uuid {
+        fn size_hint(&self) -> usize {
+            size_of::<Self>()
+        }
+
+        fn write_to(&self, buffer: &mut Vec<u8>) {
+            buffer.extend_from_slice(self.as_bytes());
+        }
+    }
+}
+
+/// Byte strings are terminated by `00 00`, with every `00` byte escaped as `00 ff`, so that a
+/// prefix sorts before any longer byte string starting with it.
+mod bytes {
+    use super::*;
+
+    const ESCAPE: u8 = 0x00;
+    const ESCAPED_ZERO: u8 = 0xff;
+    const TERMINATOR: u8 = 0x00;
+
+    impl Readable for Vec<u8> {
+        fn read_from(buffer: &mut impl Read) -> ReadResult<Self> {
+            let mut bytes = Vec::new();
+            loop {
+                match u8::read_from(buffer)? {
+                    ESCAPE => match u8::read_from(buffer)? {
+                        TERMINATOR => return Ok(bytes),
+                        ESCAPED_ZERO => bytes.push(0x00),
+                        _ => return Err(ReadError::InvalidInput),
+                    },
+                    byte => bytes.push(byte),
+                }
+            }
+        }
+    }
+
+    impl Writable for [u8] {
+        fn size_hint(&self) -> usize {
+            self.len() + // Bytes
+            self.iter().filter(|&&byte| byte == 0x00).count() + // Escapes
+            2 // Terminator
+        }
+
+        fn write_to(&self, buffer: &mut Vec<u8>) {
+            for &byte in self {
+                buffer.push(byte);
+                if byte == 0x00 {
+                    buffer.push(ESCAPED_ZERO);
+                }
+            }
+            buffer.extend_from_slice(&[ESCAPE, TERMINATOR]);
+        }
+    }
+}
+
+/// Big integers are encoded as their sign, their length and their two's complement bytes. The
+/// length of negative numbers is inverted, as longer negative numbers are smaller.
+mod big_int {
+    use super::*;
+
+    const TAG_NEGATIVE: u8 = 0x00;
+    const TAG_NON_NEGATIVE: u8 = 0x01;
+
+    impl Readable for BigInt {
+        fn read_from(buffer: &mut impl Read) -> ReadResult<Self> {
+            let length = match u8::read_from(buffer)? {
+                TAG_NEGATIVE => !u32::read_from(buffer)?,
+                TAG_NON_NEGATIVE => u32::read_from(buffer)?,
+                _ => return Err(ReadError::InvalidInput),
+            };
+            let length = usize::try_from(length).map_err(|_| ReadError::InvalidInput)?;
+            let mut bytes = vec![0; length];
+            buffer.read_exact(&mut bytes)?;
+            Ok(Self::from_signed_bytes_be(&bytes))
+        }
+    }
+
+    impl Writable for BigInt {
+        fn size_hint(&self) -> usize {
+            1 + // Sign
+            size_of::<u32>() + // Length
+            self.to_signed_bytes_be().len()
+        }
+
+        fn write_to(&self, buffer: &mut Vec<u8>) {
+            let bytes = self.to_signed_bytes_be();
+            let length = u32::try_from(bytes.len()).expect("Big integer too long");
+            if self.is_negative() {
+                TAG_NEGATIVE.write_to(buffer);
+                (!length).write_to(buffer);
+            } else {
+                TAG_NON_NEGATIVE.write_to(buffer);
+                length.write_to(buffer);
+            }
+            buffer.extend_from_slice(bytes);
+        }
+    }
+}
+
 mod string {
     use super::*;
 
@@ -344,7 +627,7 @@ mod string {
         fn write_to(&self, buffer: &mut Vec<u8>) {
             // TODO: handle longer strings?
             u16::try_from(self.len())
-                .expect("String to long")
+                .expect("String too long")
                 .write_to(buffer);
 
             buffer.extend_from_slice(self.as_bytes());
@@ -361,6 +644,19 @@ mod value {
     const TAG_DEC: u8 = 0x03;
     const TAG_STR: u8 = 0x04;
     const TAG_REF: u8 = 0x05;
+    const TAG_BOOL: u8 = 0x06;
+    const TAG_KEYWORD: u8 = 0x07;
+    const TAG_INSTANT: u8 = 0x08;
+    const TAG_UUID: u8 = 0x09;
+    const TAG_DOUBLE: u8 = 0x0a;
+    const TAG_BYTES: u8 = 0x0b;
+    const TAG_BIG_INT: u8 = 0x0c;
+    const TAG_TUPLE: u8 = 0x0d;
+
+    // Every element of a tuple is preceded by a marker, and the tuple is terminated by a lower
+    // byte, so that tuples sort element by element and before any longer tuple they prefix.
+    const TUPLE_ELEMENT: u8 = 0x01;
+    const TUPLE_END: u8 = 0x00;
 
     impl Readable for Value {
         fn read_from(buffer: &mut impl Read) -> ReadResult<Self> {
@@ -371,6 +667,23 @@ mod value {
                 TAG_DEC => Ok(Value::Decimal(Decimal::read_from(buffer)?)),
                 TAG_STR => Ok(Value::Str(String::read_from(buffer)?)),
                 TAG_REF => Ok(Value::Ref(u64::read_from(buffer)?)),
+                TAG_BOOL => Ok(Value::Bool(bool::read_from(buffer)?)),
+                TAG_KEYWORD => Ok(Value::Keyword(String::read_from(buffer)?)),
+                TAG_INSTANT => Ok(Value::Instant(instant::read_from(buffer)?)),
+                TAG_UUID => Ok(Value::Uuid(Uuid::read_from(buffer)?)),
+                TAG_DOUBLE => Ok(Value::Double(OrderedFloat::read_from(buffer)?)),
+                TAG_BYTES => Ok(Value::Bytes(Vec::read_from(buffer)?)),
+                TAG_BIG_INT => Ok(Value::BigInt(BigInt::read_from(buffer)?)),
+                TAG_TUPLE => {
+                    let mut elements = Vec::new();
+                    loop {
+                        match u8::read_from(buffer)? {
+                            TUPLE_ELEMENT => elements.push(Value::read_from(buffer)?),
+                            TUPLE_END => return Ok(Value::Tuple(elements)),
+                            _ => return Err(ReadError::InvalidInput),
+                        }
+                    }
+                }
                 _ => Err(ReadError::InvalidInput),
             }
         }
@@ -383,8 +696,20 @@ mod value {
                 Self::Nil => 0,
                 Self::Decimal(value) => value.size_hint(),
                 Self::U64(value) | Self::Ref(value) => value.size_hint(),
-                Self::I64(value) => value.size_hint(),
-                Self::Str(value) => value.size_hint(),
+                Self::I64(value) | Self::Instant(value) => value.size_hint(),
+                Self::Str(value) | Self::Keyword(value) => value.size_hint(),
+                Self::Bool(value) => value.size_hint(),
+                Self::Uuid(value) => value.size_hint(),
+                Self::Double(value) => value.size_hint(),
+                Self::Bytes(value) => value.size_hint(),
+                Self::BigInt(value) => value.size_hint(),
+                Self::Tuple(elements) => {
+                    elements
+                        .iter()
+                        .map(|element| 1 + element.size_hint())
+                        .sum::<usize>()
+                        + 1
+                }
             }
         }
 
@@ -413,6 +738,42 @@ mod value {
                     TAG_REF.write_to(buffer);
                     value.write_to(buffer);
                 }
+                Self::Bool(value) => {
+                    TAG_BOOL.write_to(buffer);
+                    value.write_to(buffer);
+                }
+                Self::Keyword(value) => {
+                    TAG_KEYWORD.write_to(buffer);
+                    value.write_to(buffer);
+                }
+                Self::Instant(value) => {
+                    TAG_INSTANT.write_to(buffer);
+                    instant::write_to(*value, buffer);
+                }
+                Self::Uuid(value) => {
+                    TAG_UUID.write_to(buffer);
+                    value.write_to(buffer);
+                }
+                Self::Double(value) => {
+                    TAG_DOUBLE.write_to(buffer);
+                    value.write_to(buffer);
+                }
+                Self::Bytes(value) => {
+                    TAG_BYTES.write_to(buffer);
+                    value.write_to(buffer);
+                }
+                Self::BigInt(value) => {
+                    TAG_BIG_INT.write_to(buffer);
+                    value.write_to(buffer);
+                }
+                Self::Tuple(elements) => {
+                    TAG_TUPLE.write_to(buffer);
+                    for element in elements {
+                        TUPLE_ELEMENT.write_to(buffer);
+                        element.write_to(buffer);
+                    }
+                    TUPLE_END.write_to(buffer);
+                }
             }
         }
     }
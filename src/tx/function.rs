@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::datom::Value;
+use crate::query::database::Database;
+use crate::query::Query;
+use crate::query::QueryResult;
+use crate::storage::attribute_resolver::AttributeResolver;
+use crate::storage::ReadStorage;
+use crate::tx::EntityOperation;
+
+pub type FunctionError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A function which is invoked by the transactor while processing a transaction. It receives a
+/// read-only view of the database at the basis of the transaction, together with the arguments
+/// it was called with, and expands into operations which become part of the transaction.
+///
+/// Since functions run inside the transactor, no other transaction can be saved between reading
+/// the database and applying the returned operations.
+#[async_trait]
+pub trait TransactionFunction<S>: Send + Sync {
+    async fn apply(
+        &self,
+        db: &DatabaseView<'_, S>,
+        args: &[Value],
+    ) -> Result<Vec<EntityOperation>, FunctionError>;
+}
+
+/// Read-only view of the database passed to transaction functions.
+pub struct DatabaseView<'a, S> {
+    db: Database,
+    storage: &'a S,
+    resolver: &'a AttributeResolver,
+}
+
+impl<'a, S: ReadStorage<'a>> DatabaseView<'a, S> {
+    pub fn new(db: Database, storage: &'a S, resolver: &'a AttributeResolver) -> Self {
+        Self {
+            db,
+            storage,
+            resolver,
+        }
+    }
+
+    pub fn basis_tx(&self) -> u64 {
+        self.db.basis_tx()
+    }
+
+    /// # Errors
+    /// Query errors, see `QueryError`
+    pub async fn query(
+        &self,
+        query: Query,
+    ) -> crate::query::Result<Vec<QueryResult<S::Error>>, S::Error> {
+        let results = self.db.query(self.storage, self.resolver, query).await?;
+        Ok(results.collect())
+    }
+}
+
+/// Registry of transaction functions, by name.
+pub struct TransactionFunctions<S> {
+    functions: HashMap<String, Arc<dyn TransactionFunction<S>>>,
+}
+
+impl<S> Default for TransactionFunctions<S> {
+    fn default() -> Self {
+        Self {
+            functions: HashMap::new(),
+        }
+    }
+}
+
+impl<S> TransactionFunctions<S> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with(mut self, name: &str, function: impl TransactionFunction<S> + 'static) -> Self {
+        self.functions.insert(name.to_string(), Arc::new(function));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn TransactionFunction<S>>> {
+        self.functions.get(name)
+    }
+}
+
+/// An invocation of a transaction function within a transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    pub args: Vec<Value>,
+}
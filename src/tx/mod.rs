@@ -1,37 +1,61 @@
+pub mod function;
+pub mod predicate;
+pub mod report;
 pub mod transactor;
 
 use std::collections::HashMap;
 
+use crate::clock::Instant;
 use crate::datom::Datom;
 use crate::datom::Op;
 use crate::datom::Value;
+use crate::query::pattern::AttributeIdentifier;
+use crate::query::pattern::LookupRef;
 use crate::schema::attribute::ValueType;
+use crate::schema::partition::Partition;
+use crate::schema::DB_ENSURE_IDENT;
+use crate::schema::DB_SPEC_IDENT_IDENT;
 use crate::storage::attribute_resolver::ResolveError;
+use crate::storage::lookup_ref::LookupRefError;
+use crate::tx::function::FunctionCall;
+use crate::tx::function::FunctionError;
 use thiserror::Error;
 
 pub type Result<T, E> = std::result::Result<T, TransactionError<E>>;
 
+/// Reserved temp ID of the transaction entity itself, used to annotate transactions (e.g. with
+/// the user who made a change) or reference them.
+pub const TX_TEMP_ID: &str = "datomic.tx";
+
+#[derive(Clone)]
 pub enum OperatedEntity {
-    New,            // Create a new entity and assign ID automatically.
-    Id(u64),        // Update existing entity by ID.
-    TempId(String), // Use a temp ID within transaction.
+    New,                  // Create a new entity and assign ID automatically.
+    Id(u64),              // Update existing entity by ID.
+    TempId(String),       // Use a temp ID within transaction.
+    LookupRef(LookupRef), // Update existing entity identified by a unique attribute value.
 }
 
+#[derive(Clone)]
 pub enum AttributeValue {
-    Value(Value),   // Set a concrete value to attribute.
-    TempId(String), // Reference a temp ID within transaction.
+    Value(Value),                 // Set a concrete value to attribute.
+    TempId(String),               // Reference a temp ID within transaction.
+    LookupRef(LookupRef),         // Reference an entity identified by a unique attribute value.
+    Entity(Box<EntityOperation>), // Reference a nested entity, operated within transaction.
 }
 
+#[derive(Clone)]
 pub struct AttributeOperation {
-    // TODO: allow to reference an attribute by ID in addition to ident
-    pub attribute: String,
+    pub attribute: AttributeIdentifier,
     pub value: AttributeValue,
     pub op: Op,
+    pub expected: Option<Value>, // Only apply if this is the current value (compare-and-swap).
 }
 
+#[derive(Clone)]
 pub struct EntityOperation {
     pub entity: OperatedEntity,
     pub attributes: Vec<AttributeOperation>,
+    pub partition: Partition, // Partition of the entity, if it's created by this operation.
 }
 
 impl EntityOperation {
@@ -40,6 +64,7 @@ impl EntityOperation {
         Self {
             entity,
             attributes: Vec::new(),
+            partition: Partition::default(),
         }
     }
 
@@ -58,46 +83,160 @@ impl EntityOperation {
         Self::new(OperatedEntity::TempId(temp_id.to_string()))
     }
 
+    /// Operates the entity of the current transaction.
+    #[must_use]
+    pub fn on_tx() -> Self {
+        Self::on_temp_id(TX_TEMP_ID)
+    }
+
+    /// Creates the operated entity in `partition`, when it's new or a temp ID. Entities are
+    /// created in `Partition::USER` by default.
+    #[must_use]
+    pub fn in_partition(mut self, partition: Partition) -> Self {
+        self.partition = partition;
+        self
+    }
+
     #[must_use]
-    pub fn assert(self, attribute: &str, value: impl Into<Value>) -> Self {
+    pub fn on_lookup_ref(attribute: &str, value: impl Into<Value>) -> Self {
+        Self::new(OperatedEntity::LookupRef(LookupRef::new(attribute, value)))
+    }
+
+    #[must_use]
+    pub fn assert(
+        self,
+        attribute: impl Into<AttributeIdentifier>,
+        value: impl Into<Value>,
+    ) -> Self {
         self.set(
-            attribute.to_string(),
+            attribute.into(),
             AttributeValue::Value(value.into()),
             Op::Assert,
         )
     }
 
     #[must_use]
-    pub fn retract(self, attribute: &str, value: impl Into<Value>) -> Self {
+    pub fn retract(
+        self,
+        attribute: impl Into<AttributeIdentifier>,
+        value: impl Into<Value>,
+    ) -> Self {
         self.set(
-            attribute.to_string(),
+            attribute.into(),
             AttributeValue::Value(value.into()),
             Op::Retract,
         )
     }
 
     #[must_use]
-    pub fn set_reference(self, attribute: &str, temp_id: &str) -> Self {
+    pub fn set_reference(self, attribute: impl Into<AttributeIdentifier>, temp_id: &str) -> Self {
         self.set(
-            attribute.to_string(),
+            attribute.into(),
             AttributeValue::TempId(temp_id.to_string()),
             Op::Assert,
         )
     }
 
-    fn set(mut self, attribute: String, value: AttributeValue, op: Op) -> Self {
+    #[must_use]
+    pub fn set_lookup_ref(
+        self,
+        attribute: impl Into<AttributeIdentifier>,
+        lookup_ref: LookupRef,
+    ) -> Self {
+        self.set(
+            attribute.into(),
+            AttributeValue::LookupRef(lookup_ref),
+            Op::Assert,
+        )
+    }
+
+    /// References the entity operated by `entity`, which is transacted along with this one. A
+    /// nested new entity is created with a generated ID.
+    #[must_use]
+    pub fn set_entity(
+        self,
+        attribute: impl Into<AttributeIdentifier>,
+        entity: EntityOperation,
+    ) -> Self {
+        self.set(
+            attribute.into(),
+            AttributeValue::Entity(Box::new(entity)),
+            Op::Assert,
+        )
+    }
+
+    /// Validates that the entity has all attributes required by the entity spec identified by
+    /// `spec` once the transaction is applied, otherwise the transaction fails. Nothing is saved
+    /// for the entity by ensuring a spec.
+    #[must_use]
+    pub fn ensure(self, spec: &str) -> Self {
+        self.set(
+            DB_ENSURE_IDENT.into(),
+            AttributeValue::LookupRef(LookupRef::new(DB_SPEC_IDENT_IDENT, Value::keyword(spec))),
+            Op::Assert,
+        )
+    }
+
+    /// Asserts `value` only if the current value of `attribute` is `expected`, otherwise the
+    /// transaction fails. Use `Value::Nil` as `expected` to require that no value is set.
+    #[must_use]
+    pub fn compare_and_swap(
+        mut self,
+        attribute: impl Into<AttributeIdentifier>,
+        expected: impl Into<Value>,
+        value: impl Into<Value>,
+    ) -> Self {
+        self.attributes.push(AttributeOperation {
+            attribute: attribute.into(),
+            value: AttributeValue::Value(value.into()),
+            op: Op::Assert,
+            expected: Some(expected.into()),
+        });
+        self
+    }
+
+    fn set(mut self, attribute: AttributeIdentifier, value: AttributeValue, op: Op) -> Self {
         self.attributes.push(AttributeOperation {
             attribute,
             value,
             op,
+            expected: None,
         });
         self
     }
 }
 
+/// Permanently removes datoms of an entity from storage, including their history.
+#[derive(Clone)]
+pub struct Excision {
+    pub entity: u64,
+    pub attributes: Vec<AttributeIdentifier>, // Only remove values of these, all if empty.
+}
+
+impl Excision {
+    #[must_use]
+    pub fn new(entity: u64) -> Self {
+        Self {
+            entity,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Limits the excision to values of `attribute`. Can be used multiple times.
+    #[must_use]
+    pub fn attribute(mut self, attribute: impl Into<AttributeIdentifier>) -> Self {
+        self.attributes.push(attribute.into());
+        self
+    }
+}
+
 #[derive(Default)]
 pub struct Transaction {
     pub operations: Vec<EntityOperation>,
+    pub retracted_entities: Vec<u64>,
+    pub excisions: Vec<Excision>,
+    pub calls: Vec<FunctionCall>,
+    pub tx_time: Option<Instant>, // Overrides the transactor's clock, e.g. for data imports.
 }
 
 impl Transaction {
@@ -111,12 +250,48 @@ impl Transaction {
         self.operations.push(o.into());
         self
     }
+
+    /// Retracts all attributes of an entity and all references to it. Entities referenced through
+    /// component attributes are retracted recursively.
+    #[must_use]
+    pub fn retract_entity(mut self, entity_id: u64) -> Self {
+        self.retracted_entities.push(entity_id);
+        self
+    }
+
+    /// Permanently removes the datoms selected by `excision`, such that no query can see them,
+    /// including queries of earlier points in time. The transaction records the excision as an
+    /// entity with `db/excise` and `db/excise/attrs`.
+    #[must_use]
+    pub fn excise(mut self, excision: Excision) -> Self {
+        self.excisions.push(excision);
+        self
+    }
+
+    /// Sets the time of the transaction, instead of reading it from the transactor's clock.
+    #[must_use]
+    pub fn with_tx_time(mut self, time: Instant) -> Self {
+        self.tx_time = Some(time);
+        self
+    }
+
+    /// Invokes the transaction function registered as `name` with `args`. The operations
+    /// returned by the function are processed as part of this transaction.
+    #[must_use]
+    pub fn call(mut self, name: &str, args: Vec<Value>) -> Self {
+        self.calls.push(FunctionCall {
+            name: name.to_string(),
+            args,
+        });
+        self
+    }
 }
 
 #[derive(Debug)]
 pub struct TransctionResult {
     pub tx_id: u64,
     pub tx_data: Vec<Datom>,
+    pub redundant: Vec<Datom>, // Datoms which were dropped since they wouldn't change the database.
     pub temp_ids: HashMap<String, u64>,
 }
 
@@ -136,6 +311,74 @@ pub enum TransactionError<S> {
     TempIdNotFound(String),
     #[error("resolve error")]
     ResolveError(#[from] ResolveError<S>),
+    #[error("lookup ref error")]
+    LookupRefError(#[from] LookupRefError<S>),
     #[error("duplicate value for attribute {attribute}")]
     DuplicateUniqueValue { attribute: u64, value: Value },
+    #[error("conflicting upsert, entity resolves to both {entity} and {other}")]
+    UpsertConflict { entity: u64, other: u64 },
+    #[error("value of attribute {attribute} is both asserted and retracted for entity {entity}")]
+    ConflictingDatoms {
+        entity: u64,
+        attribute: u64,
+        value: Value,
+    },
+    #[error("compare-and-swap is not supported for attribute {0} with cardinality many")]
+    CompareAndSwapCardinalityMany(u64),
+    #[error("compare-and-swap failed for attribute {attribute} of entity {entity}")]
+    CompareAndSwapFailed {
+        entity: u64,
+        attribute: u64,
+        expected: Value,
+        actual: Value,
+    },
+    #[error("attribute {attribute} of entity {entity} references missing entity {target}")]
+    MissingReference {
+        entity: u64,
+        attribute: u64,
+        target: u64,
+    },
+    #[error("invalid alteration of attribute {attribute}: {reason}")]
+    InvalidAlteration {
+        attribute: u64,
+        reason: &'static str,
+    },
+    #[error(
+        "value of attribute {attribute} takes {size} bytes, more than the maximum of {max_size}"
+    )]
+    ValueTooLarge {
+        attribute: u64,
+        size: usize,
+        max_size: usize,
+    },
+    #[error("value of attribute {0} contains a string or big integer which is too long to store")]
+    ValueNotEncodable(u64),
+    #[error("composite tuple attribute {0} is maintained by the transactor")]
+    CompositeTupleTransacted(u64),
+    #[error("attribute predicate `{0}` not found")]
+    PredicateNotFound(String),
+    #[error("value of attribute {attribute} doesn't satisfy predicate `{predicate}`")]
+    PredicateFailed {
+        attribute: u64,
+        predicate: String,
+        value: Value,
+    },
+    #[error("entity {entity} is missing attribute `{attribute}` required by spec `{spec}`")]
+    SpecNotSatisfied {
+        entity: u64,
+        spec: String,
+        attribute: String,
+    },
+    #[error("attribute {0} can't be excised")]
+    ExcisedAttribute(u64),
+    #[error("reverse attribute `{0}` must reference an entity")]
+    InvalidReverseReference(String),
+    #[error("transaction function `{0}` not found")]
+    FunctionNotFound(String),
+    #[error("transaction function `{name}` failed")]
+    FunctionFailed {
+        name: String,
+        #[source]
+        source: FunctionError,
+    },
 }
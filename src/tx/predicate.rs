@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::mem::discriminant;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::datom::Value;
+
+/// A predicate which asserted values of an attribute must satisfy, on top of matching its value
+/// type. Attributes reference predicates by the name they are registered with, see
+/// `AttributeDefinition::with_predicate`.
+pub trait AttributePredicate: Send + Sync {
+    fn test(&self, value: &Value) -> bool;
+}
+
+impl<F: Fn(&Value) -> bool + Send + Sync> AttributePredicate for F {
+    fn test(&self, value: &Value) -> bool {
+        self(value)
+    }
+}
+
+/// Accepts strings and keywords of at most this many characters.
+pub struct MaxLength(pub usize);
+
+impl AttributePredicate for MaxLength {
+    fn test(&self, value: &Value) -> bool {
+        match value {
+            Value::Str(string) | Value::Keyword(string) => string.chars().count() <= self.0,
+            _ => false,
+        }
+    }
+}
+
+/// Accepts values of the same type as the bounds, between them (inclusive).
+pub struct Range {
+    min: Value,
+    max: Value,
+}
+
+impl Range {
+    pub fn new(min: impl Into<Value>, max: impl Into<Value>) -> Self {
+        Self {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+impl AttributePredicate for Range {
+    fn test(&self, value: &Value) -> bool {
+        discriminant(value) == discriminant(&self.min)
+            && discriminant(value) == discriminant(&self.max)
+            && (&self.min..=&self.max).contains(&value)
+    }
+}
+
+/// Accepts strings and keywords which match a regular expression.
+pub struct Matches(Regex);
+
+impl Matches {
+    /// # Errors
+    /// If `pattern` is not a valid regular expression
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self(Regex::new(pattern)?))
+    }
+}
+
+impl AttributePredicate for Matches {
+    fn test(&self, value: &Value) -> bool {
+        match value {
+            Value::Str(string) | Value::Keyword(string) => self.0.is_match(string),
+            _ => false,
+        }
+    }
+}
+
+/// Registry of attribute predicates, by name.
+#[derive(Clone, Default)]
+pub struct AttributePredicates {
+    predicates: HashMap<String, Arc<dyn AttributePredicate>>,
+}
+
+impl AttributePredicates {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with(mut self, name: &str, predicate: impl AttributePredicate + 'static) -> Self {
+        self.predicates
+            .insert(name.to_string(), Arc::new(predicate));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn AttributePredicate>> {
+        self.predicates.get(name)
+    }
+}
+
+impl Debug for AttributePredicates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.predicates.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_length() {
+        let predicate = MaxLength(3);
+
+        assert!(predicate.test(&Value::str("foo")));
+        assert!(predicate.test(&Value::str("äöü")));
+        assert!(!predicate.test(&Value::str("foob")));
+        assert!(!predicate.test(&Value::U64(1)));
+    }
+
+    #[test]
+    fn range() {
+        let predicate = Range::new(1i64, 10i64);
+
+        assert!(predicate.test(&Value::I64(1)));
+        assert!(predicate.test(&Value::I64(10)));
+        assert!(!predicate.test(&Value::I64(0)));
+        assert!(!predicate.test(&Value::I64(11)));
+        assert!(!predicate.test(&Value::U64(5)));
+    }
+
+    #[test]
+    fn matches() {
+        let predicate = Matches::new("^[a-z]+@[a-z]+\\.com$").expect("Invalid pattern");
+
+        assert!(predicate.test(&Value::str("john@beatles.com")));
+        assert!(!predicate.test(&Value::str("john")));
+        assert!(!predicate.test(&Value::U64(1)));
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::datom::Datom;
+use crate::query::database::Database;
+use crate::tx::TransctionResult;
+
+/// Describes a transaction after it was saved: the database values before and after the
+/// transaction, the datoms it produced and the IDs assigned to its temp IDs. Datoms which would
+/// not have changed the database are reported as `redundant` instead of being saved.
+#[derive(Debug, Clone)]
+pub struct TransactionReport {
+    pub db_before: Database,
+    pub db_after: Database,
+    pub tx_data: Vec<Datom>,
+    pub redundant: Vec<Datom>,
+    pub temp_ids: HashMap<String, u64>,
+}
+
+impl TransactionReport {
+    pub fn new(db_before: Database, result: TransctionResult) -> Self {
+        Self {
+            db_before,
+            db_after: Database::new(result.tx_id),
+            tx_data: result.tx_data,
+            redundant: result.redundant,
+            temp_ids: result.temp_ids,
+        }
+    }
+
+    /// Returns `true` if the transaction did not change any entity other than the transaction
+    /// entity itself.
+    pub fn is_empty(&self) -> bool {
+        let tx_id = self.db_after.basis_tx();
+        self.tx_data.iter().all(|datom| datom.entity == tx_id)
+    }
+}
+
+pub type TxReportStream = BroadcastStream<Arc<TransactionReport>>;
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Broadcasts reports of saved transactions to all subscribed listeners.
+///
+/// Every subscriber gets its own stream of reports, starting with the first report published
+/// after subscribing. A subscriber falling behind by more than the queue's capacity misses the
+/// oldest reports, and its stream yields a `Lagged` error with the number of skipped reports.
+#[derive(Clone)]
+pub struct TxReportQueue {
+    sender: broadcast::Sender<Arc<TransactionReport>>,
+}
+
+impl Default for TxReportQueue {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl TxReportQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Publishes `report` to all current subscribers.
+    /// Reports published while nobody is subscribed are dropped.
+    pub fn publish(&self, report: TransactionReport) {
+        // Sending only fails when there are no subscribers, which is fine.
+        let _ = self.sender.send(Arc::new(report));
+    }
+
+    pub fn subscribe(&self) -> TxReportStream {
+        BroadcastStream::new(self.sender.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use super::*;
+
+    fn report(tx_id: u64) -> TransactionReport {
+        let result = TransctionResult {
+            tx_id,
+            tx_data: vec![Datom::add(tx_id, 1, "foo", tx_id)],
+            redundant: Vec::new(),
+            temp_ids: HashMap::from([("foo".to_string(), tx_id + 1)]),
+        };
+        TransactionReport::new(Database::new(tx_id - 1), result)
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_published_reports() {
+        let queue = TxReportQueue::new();
+        let mut subscriber1 = queue.subscribe();
+        let mut subscriber2 = queue.subscribe();
+
+        queue.publish(report(42));
+
+        for subscriber in [&mut subscriber1, &mut subscriber2] {
+            let received = subscriber.next().await.unwrap().unwrap();
+            assert_eq!(41, received.db_before.basis_tx());
+            assert_eq!(42, received.db_after.basis_tx());
+            assert_eq!(vec![Datom::add(42, 1, "foo", 42)], received.tx_data);
+            assert_eq!(Some(&43), received.temp_ids.get("foo"));
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_only_receive_reports_published_after_subscribing() {
+        let queue = TxReportQueue::new();
+        queue.publish(report(1));
+
+        let mut subscriber = queue.subscribe();
+        queue.publish(report(2));
+
+        let received = subscriber.next().await.unwrap().unwrap();
+        assert_eq!(2, received.db_after.basis_tx());
+    }
+}
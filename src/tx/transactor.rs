@@ -1,55 +1,149 @@
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
 
 use crate::clock::Instant;
+use crate::query::database::Database;
+use crate::query::pattern::AttributeIdentifier;
 use crate::schema::attribute::*;
+use crate::schema::partition::Partition;
 use crate::schema::*;
 use crate::storage::attribute_resolver::*;
+use crate::storage::lookup_ref::resolve_lookup_ref;
 use crate::storage::restricts::Restricts;
+use crate::storage::serde::{is_encodable, Writable};
 use crate::storage::ReadStorage;
+use crate::tx::function::{DatabaseView, TransactionFunctions};
+use crate::tx::predicate::{AttributePredicate, AttributePredicates};
 use crate::tx::{
-    AttributeValue, Datom, EntityOperation, OperatedEntity, Result, Transaction, TransactionError,
-    TransctionResult, Value, ValueType,
+    AttributeOperation, AttributeValue, Datom, EntityOperation, Excision, Op, OperatedEntity,
+    Result, Transaction, TransactionError, TransctionResult, Value, ValueType, TX_TEMP_ID,
 };
 
+/// Default for `TransactorConfig::max_value_size`. Strings can't be longer than this anyway.
+pub const DEFAULT_MAX_VALUE_SIZE: usize = u16::MAX as usize;
+
+#[derive(Clone, Debug)]
+pub struct TransactorConfig {
+    /// Maximum size in bytes of an encoded asserted value. Every value is stored in several
+    /// indexes, so a single huge value is costly.
+    pub max_value_size: usize,
+    /// Predicates which attributes can reference by name, see `AttributeDefinition::with_predicate`.
+    pub predicates: AttributePredicates,
+}
+
+impl Default for TransactorConfig {
+    fn default() -> Self {
+        Self {
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            predicates: AttributePredicates::new(),
+        }
+    }
+}
+
+impl TransactorConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_max_value_size(mut self, max_value_size: usize) -> Self {
+        self.max_value_size = max_value_size;
+        self
+    }
+
+    /// Registers `predicate` as `name`, for attributes to reference.
+    #[must_use]
+    pub fn with_predicate(
+        mut self,
+        name: &str,
+        predicate: impl AttributePredicate + 'static,
+    ) -> Self {
+        self.predicates = self.predicates.with(name, predicate);
+        self
+    }
+}
+
 /// # Errors
 /// Storage related errors
 pub async fn transact<'a, S: ReadStorage<'a>>(
     storage: &'a S,
-    resolver: &AttributeResolver,
+    resolver: &'a AttributeResolver,
+    functions: &TransactionFunctions<S>,
+    config: &TransactorConfig,
     now: Instant,
     transaction: Transaction,
 ) -> Result<TransctionResult, S::Error> {
-    let next_id = NextId(storage.latest_entity_id()?);
-    let mut builder = ResultBuilder::from(&transaction.operations, now, next_id)?;
-    for operation in transaction.operations {
+    let latest_entity_id = storage.latest_entity_id()?;
+    let mut operations = transaction.operations;
+    if !transaction.calls.is_empty() {
+        let db = DatabaseView::new(Database::latest(latest_entity_id), storage, resolver);
+        for call in transaction.calls {
+            let function = functions
+                .get(&call.name)
+                .ok_or_else(|| TransactionError::FunctionNotFound(call.name.clone()))?;
+            let expanded = function.apply(&db, &call.args).await.map_err(|source| {
+                TransactionError::FunctionFailed {
+                    name: call.name,
+                    source,
+                }
+            })?;
+            operations.extend(expanded);
+        }
+    }
+
+    let Flattened {
+        operations,
+        references,
+        ..
+    } = Flattened::from(operations)?;
+    let next_id = NextId(latest_entity_id);
+    let now = transaction.tx_time.unwrap_or(now);
+    let mut builder = ResultBuilder::from(&operations, config, now, next_id)?;
+    builder
+        .resolve_upserts(storage, resolver, &operations)
+        .await?;
+    for operation in operations.into_iter().chain(references) {
         builder.update(storage, resolver, operation).await?;
     }
+    for entity in transaction.retracted_entities {
+        builder.retract_entity(storage, resolver, entity).await?;
+    }
+    for excision in transaction.excisions {
+        builder.excise(storage, resolver, excision).await?;
+    }
+    builder.update_composite_tuples(storage, resolver).await?;
+    builder.verify_specs(storage, resolver).await?;
     Ok(builder.build())
 }
 
-struct ResultBuilder {
+struct ResultBuilder<'c> {
     tx_id: u64,
     next_id: NextId,
     datoms: Vec<Datom>,
+    redundant: Vec<Datom>,
     temp_ids: HashMap<String, u64>,
-    unique_values: HashSet<(u64, Value)>,
+    unique_values: HashMap<(u64, Value), u64>,
+    config: &'c TransactorConfig,
 }
 
-impl ResultBuilder {
+impl<'c> ResultBuilder<'c> {
     pub fn from<E>(
         operations: &[EntityOperation],
+        config: &'c TransactorConfig,
         Instant(now): Instant,
         mut next_id: NextId,
     ) -> Result<Self, E> {
-        let tx_id = next_id.get();
-        let temp_ids = generate_temp_ids(operations, &mut next_id)?;
+        let tx_id = next_id.get(Partition::TX);
+        let temp_ids = generate_temp_ids(operations, tx_id, &mut next_id)?;
         Ok(Self {
             tx_id,
             next_id,
             temp_ids,
             datoms: vec![Datom::add(tx_id, DB_TX_TIME_ID, now, tx_id)],
-            unique_values: HashSet::new(),
+            redundant: Vec::new(),
+            unique_values: HashMap::new(),
+            config,
         })
     }
 
@@ -59,33 +153,73 @@ impl ResultBuilder {
         resolver: &AttributeResolver,
         operation: EntityOperation,
     ) -> Result<(), S::Error> {
-        let entity = self.resolve_entity(operation.entity)?;
+        let upserted = match operation.entity {
+            OperatedEntity::New => {
+                self.find_upserted(storage, resolver, &operation.attributes)
+                    .await?
+            }
+            _ => None,
+        };
+        let entity = match upserted {
+            Some(entity) => entity,
+            None => {
+                self.resolve_entity(storage, resolver, operation.entity, operation.partition)
+                    .await?
+            }
+        };
         let mut retract_attributes = HashSet::with_capacity(operation.attributes.len());
         for attribute_value in operation.attributes {
             let attribute = resolver
-                .resolve(storage, &attribute_value.attribute, self.tx_id)
+                .resolve_identifier(storage, &attribute_value.attribute, self.tx_id)
                 .await?;
 
-            if attribute.definition.cardinality == Cardinality::One {
-                // Values of attributes with cardinality `Cardinality::One` should be retracted
-                // before asserting new values.
-                retract_attributes.insert(attribute.id);
+            if let Some(expected) = attribute_value.expected {
+                self.verify_current_value(storage, entity, &attribute, expected)?;
             }
 
-            let value = self.resolve_value(attribute_value.value)?;
+            let value = match self
+                .resolve_value(storage, resolver, attribute_value.value)
+                .await?
+            {
+                // Reference by the `db/ident` of an entity, such as an enum value
+                Value::Keyword(ident) if attribute.definition.value_type == ValueType::Ref => {
+                    Value::Ref(resolver.resolve_entity(storage, &ident, self.tx_id).await?)
+                }
+                value => value,
+            };
             verify_type(&attribute, &value)?;
-            if attribute.definition.unique {
-                self.verify_uniqueness_tx(&attribute, &value)?;
-                self.verify_uniqueness_db(&attribute, &value, storage)?;
+            if attribute_value.op == Op::Assert {
+                self.verify_value_size(&attribute, &value)?;
+                self.verify_predicates(&attribute, &value)?;
+                self.verify_alteration(storage, resolver, entity, attribute.id, &value)
+                    .await?;
+            }
+            if let (true, Value::Ref(target)) = (attribute.definition.verify_refs, &value) {
+                self.verify_reference(storage, entity, &attribute, *target)?;
+            }
+            if attribute.definition.unique.is_some() {
+                self.verify_uniqueness_tx(entity, &attribute, &value)?;
+                self.verify_uniqueness_db(entity, &attribute, &value, storage)?;
             }
 
-            self.datoms.push(Datom {
+            let datom = Datom {
                 entity,
                 attribute: attribute.id,
                 value,
                 tx: self.tx_id,
                 op: attribute_value.op,
-            });
+            };
+            if self.is_redundant(storage, &datom)? {
+                self.redundant.push(datom);
+                continue;
+            }
+
+            if datom.op == Op::Assert && attribute.definition.cardinality == Cardinality::One {
+                // Values of attributes with cardinality `Cardinality::One` should be retracted
+                // before asserting new values.
+                retract_attributes.insert(attribute.id);
+            }
+            self.datoms.push(datom);
         }
 
         for attribute_id in retract_attributes {
@@ -95,10 +229,251 @@ impl ResultBuilder {
         Ok(())
     }
 
-    pub fn build(self) -> TransctionResult {
+    /// Resolves temp IDs of operations which assert existing values of identity attributes to
+    /// the entities which already have these values.
+    pub async fn resolve_upserts<'a, S: ReadStorage<'a>>(
+        &mut self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+        operations: &[EntityOperation],
+    ) -> Result<(), S::Error> {
+        for operation in operations {
+            if let OperatedEntity::TempId(temp_id) = &operation.entity {
+                if let Some(entity) = self
+                    .find_upserted(storage, resolver, &operation.attributes)
+                    .await?
+                {
+                    self.temp_ids.insert(temp_id.clone(), entity);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn find_upserted<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+        attributes: &[AttributeOperation],
+    ) -> Result<Option<u64>, S::Error> {
+        let mut upserted = None;
+        for attribute_value in attributes {
+            let AttributeValue::Value(value) = &attribute_value.value else {
+                continue;
+            };
+            if attribute_value.op != Op::Assert {
+                continue;
+            }
+            let attribute = resolver
+                .resolve_identifier(storage, &attribute_value.attribute, self.tx_id)
+                .await?;
+            if attribute.definition.unique != Some(Unique::Identity) {
+                continue;
+            }
+            // [?entity attribute value]
+            let restricts = Restricts::new(self.tx_id)
+                .with_attribute(attribute.id)
+                .with_value(value.clone());
+            let existing = storage.find(restricts).next().transpose()?;
+            match (upserted, existing) {
+                (Some(entity), Some(datom)) if entity != datom.entity => {
+                    return Err(TransactionError::UpsertConflict {
+                        entity,
+                        other: datom.entity,
+                    });
+                }
+                (_, Some(datom)) => upserted = Some(datom.entity),
+                _ => (),
+            }
+        }
+        Ok(upserted)
+    }
+
+    pub async fn retract_entity<'a, S: ReadStorage<'a>>(
+        &mut self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+        entity: u64,
+    ) -> Result<(), S::Error> {
+        let mut entities = vec![entity];
+        let mut retracted = HashSet::new();
+        while let Some(entity) = entities.pop() {
+            if !retracted.insert(entity) {
+                continue;
+            }
+
+            // [entity ?attribute ?value]
+            let restricts = Restricts::new(self.tx_id).with_entity(entity);
+            let datoms = storage
+                .find(restricts)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            // [?referencing ?attribute entity]
+            let restricts = Restricts::new(self.tx_id).with_value(Value::Ref(entity));
+            let references = storage
+                .find(restricts)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            for datom in datoms.into_iter().chain(references) {
+                if let Value::Ref(component) = datom.value {
+                    let attribute = resolver
+                        .resolve_id(storage, datom.attribute, self.tx_id)
+                        .await?;
+                    if attribute.definition.is_component && datom.entity == entity {
+                        entities.push(component);
+                    }
+                }
+                let retracted =
+                    Datom::retract(datom.entity, datom.attribute, datom.value, self.tx_id);
+                self.push_retraction(storage, retracted)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records `excision` as a new entity. Storages remove the excised datoms when saving it.
+    pub async fn excise<'a, S: ReadStorage<'a>>(
+        &mut self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+        excision: Excision,
+    ) -> Result<(), S::Error> {
+        match resolver
+            .resolve_id(storage, excision.entity, self.tx_id)
+            .await
+        {
+            Ok(_) => return Err(TransactionError::ExcisedAttribute(excision.entity)),
+            Err(ResolveError::IdNotFound(_)) => (),
+            Err(err) => return Err(err.into()),
+        }
+        let entity = self.next_id.get(Partition::USER);
+        let value = Value::Ref(excision.entity);
+        self.datoms
+            .push(Datom::add(entity, DB_EXCISE_ID, value, self.tx_id));
+        for attribute in excision.attributes {
+            let attribute = resolver
+                .resolve_identifier(storage, &attribute, self.tx_id)
+                .await?;
+            let value = Value::Ref(attribute.id);
+            self.datoms
+                .push(Datom::add(entity, DB_EXCISE_ATTRS_ID, value, self.tx_id));
+        }
+        Ok(())
+    }
+
+    /// Validates the entity specs ensured by the transaction against the entities once the
+    /// transaction is applied. `db/ensure` datoms are dropped rather than saved.
+    pub async fn verify_specs<'a, S: ReadStorage<'a>>(
+        &mut self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+    ) -> Result<(), S::Error> {
+        let ensured: Vec<_> = self
+            .datoms
+            .iter()
+            .filter(|datom| datom.attribute == DB_ENSURE_ID && datom.op == Op::Assert)
+            .filter_map(|datom| match datom.value {
+                Value::Ref(spec) => Some((datom.entity, spec)),
+                _ => None,
+            })
+            .collect();
+        self.datoms.retain(|datom| datom.attribute != DB_ENSURE_ID);
+        for (entity, spec) in ensured {
+            // [spec ?attribute ?value]
+            let restricts = Restricts::new(self.tx_id).with_entity(spec);
+            let datoms = storage
+                .find(restricts)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let ident = datoms.iter().find_map(|datom| match &datom.value {
+                Value::Keyword(ident) if datom.attribute == DB_SPEC_IDENT_ID => Some(ident),
+                _ => None,
+            });
+            let required = datoms.iter().filter_map(|datom| match &datom.value {
+                Value::Keyword(ident) if datom.attribute == DB_SPEC_ATTRS_ID => Some(ident),
+                _ => None,
+            });
+            for attribute_ident in required {
+                let attribute = resolver
+                    .resolve(storage, attribute_ident, self.tx_id)
+                    .await?;
+                if self.current_value(storage, entity, attribute.id)? == Value::Nil {
+                    return Err(TransactionError::SpecNotSatisfied {
+                        entity,
+                        spec: ident.cloned().unwrap_or_default(),
+                        attribute: attribute_ident.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Asserts composite tuples of entities whose values of the tuples' attributes were changed
+    /// by the transaction, and retracts the tuples they replace.
+    pub async fn update_composite_tuples<'a, S: ReadStorage<'a>>(
+        &mut self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+    ) -> Result<(), S::Error> {
+        // [?composite :db/attr/tupleAttrs _]
+        let restricts = Restricts::new(self.tx_id).with_attribute(DB_ATTR_TUPLE_ATTRS_ID);
+        let composites = storage
+            .find(restricts)
+            .map(|datom| datom.map(|datom| datom.entity))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for composite in composites {
+            let composite = resolver.resolve_id(storage, composite, self.tx_id).await?;
+            let Some(TupleType::Composite(idents)) = &composite.definition.tuple_type else {
+                continue;
+            };
+            let mut components = Vec::with_capacity(idents.len());
+            for ident in idents {
+                components.push(resolver.resolve(storage, ident, self.tx_id).await?.id);
+            }
+            let entities = self
+                .datoms
+                .iter()
+                .filter(|datom| components.contains(&datom.attribute))
+                .map(|datom| datom.entity)
+                .collect::<BTreeSet<_>>();
+            for entity in entities {
+                let mut elements = Vec::with_capacity(components.len());
+                for &component in &components {
+                    elements.push(self.current_value(storage, entity, component)?);
+                }
+                let tuple = if elements.iter().all(|element| *element == Value::Nil) {
+                    Value::Nil
+                } else {
+                    Value::Tuple(elements)
+                };
+                let previous = self.current_value(storage, entity, composite.id)?;
+                if tuple == previous {
+                    continue;
+                }
+                if previous != Value::Nil {
+                    let retracted = Datom::retract(entity, composite.id, previous, self.tx_id);
+                    self.datoms.push(retracted);
+                }
+                if tuple != Value::Nil {
+                    if composite.definition.unique.is_some() {
+                        self.verify_uniqueness_tx(entity, &composite, &tuple)?;
+                        self.verify_uniqueness_db(entity, &composite, &tuple, storage)?;
+                    }
+                    self.datoms
+                        .push(Datom::add(entity, composite.id, tuple, self.tx_id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(mut self) -> TransctionResult {
+        self.temp_ids.retain(|temp_id, _| {
+            temp_id != TX_TEMP_ID && !temp_id.starts_with(NESTED_TEMP_ID_PREFIX)
+        });
         TransctionResult {
             tx_id: self.tx_id,
             tx_data: self.datoms,
+            redundant: self.redundant,
             temp_ids: self.temp_ids,
         }
     }
@@ -115,11 +490,88 @@ impl ResultBuilder {
             .with_attribute(attribute);
         for datom in storage.find(restricts) {
             let retracted = Datom::retract(entity, attribute, datom?.value, self.tx_id);
+            self.push_retraction(storage, retracted)?;
+        }
+        Ok(())
+    }
+
+    /// Pushes a retraction implied by the transaction, unless it's redundant (e.g. the value was
+    /// already retracted explicitly).
+    fn push_retraction<'a, S: ReadStorage<'a>>(
+        &mut self,
+        storage: &'a S,
+        retracted: Datom,
+    ) -> Result<(), S::Error> {
+        if !self.is_redundant(storage, &retracted)? {
             self.datoms.push(retracted);
         }
         Ok(())
     }
 
+    /// Returns the value of a cardinality one `attribute` of `entity` once the transaction is
+    /// saved, or `Value::Nil` if it has none.
+    fn current_value<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        entity: u64,
+        attribute: u64,
+    ) -> Result<Value, S::Error> {
+        let mut retracted = Vec::new();
+        for datom in self.datoms.iter().rev() {
+            if datom.entity != entity || datom.attribute != attribute {
+                continue;
+            }
+            match datom.op {
+                Op::Assert => return Ok(datom.value.clone()),
+                Op::Retract => retracted.push(&datom.value),
+            }
+        }
+        // [entity attribute ?value]
+        let restricts = Restricts::new(self.tx_id)
+            .with_entity(entity)
+            .with_attribute(attribute);
+        for datom in storage.find(restricts) {
+            let datom = datom?;
+            if !retracted.contains(&&datom.value) {
+                return Ok(datom.value);
+            }
+        }
+        Ok(Value::Nil)
+    }
+
+    /// A datom is redundant if saving it would not change the database, i.e. it asserts a value
+    /// which is already asserted, or retracts a value which is not. Asserting and retracting the
+    /// same value in a single transaction is a conflict.
+    fn is_redundant<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        datom: &Datom,
+    ) -> Result<bool, S::Error> {
+        let conflicts = |other: &Datom| {
+            other.entity == datom.entity
+                && other.attribute == datom.attribute
+                && other.value == datom.value
+                && other.op != datom.op
+        };
+        if self.datoms.iter().chain(&self.redundant).any(conflicts) {
+            return Err(TransactionError::ConflictingDatoms {
+                entity: datom.entity,
+                attribute: datom.attribute,
+                value: datom.value.clone(),
+            });
+        }
+        if self.datoms.contains(datom) {
+            return Ok(true);
+        }
+        // [entity attribute value]
+        let restricts = Restricts::new(self.tx_id)
+            .with_entity(datom.entity)
+            .with_attribute(datom.attribute)
+            .with_value(datom.value.clone());
+        let exists = storage.find(restricts).next().transpose()?.is_some();
+        Ok(exists == (datom.op == Op::Assert))
+    }
+
     fn temp_id<E>(&self, temp_id: &str) -> Result<u64, E> {
         self.temp_ids
             .get(temp_id)
@@ -127,60 +579,349 @@ impl ResultBuilder {
             .ok_or_else(|| TransactionError::TempIdNotFound(temp_id.to_string()))
     }
 
-    fn resolve_entity<E>(&mut self, entity: OperatedEntity) -> Result<u64, E> {
+    async fn resolve_entity<'a, S: ReadStorage<'a>>(
+        &mut self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+        entity: OperatedEntity,
+        partition: Partition,
+    ) -> Result<u64, S::Error> {
         match entity {
-            OperatedEntity::New => Ok(self.next_id.get()),
+            OperatedEntity::New => Ok(self.next_id.get(partition)),
             OperatedEntity::Id(id) => Ok(id),
             OperatedEntity::TempId(temp_id) => self.temp_id(&temp_id),
+            OperatedEntity::LookupRef(lookup_ref) => {
+                let entity = resolve_lookup_ref(storage, resolver, &lookup_ref, self.tx_id).await?;
+                Ok(entity)
+            }
         }
     }
 
-    fn resolve_value<E>(&self, attribute_value: AttributeValue) -> Result<Value, E> {
+    async fn resolve_value<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+        attribute_value: AttributeValue,
+    ) -> Result<Value, S::Error> {
         match attribute_value {
             AttributeValue::Value(value) => Ok(value),
             AttributeValue::TempId(temp_id) => self.temp_id(&temp_id).map(Value::Ref),
+            AttributeValue::LookupRef(lookup_ref) => {
+                let entity = resolve_lookup_ref(storage, resolver, &lookup_ref, self.tx_id).await?;
+                Ok(Value::Ref(entity))
+            }
+            AttributeValue::Entity(_) => {
+                unreachable!("nested entities are flattened before values are resolved")
+            }
+        }
+    }
+
+    fn verify_current_value<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        entity: u64,
+        attribute: &Attribute,
+        expected: Value,
+    ) -> Result<(), S::Error> {
+        if attribute.definition.cardinality == Cardinality::Many {
+            return Err(TransactionError::CompareAndSwapCardinalityMany(
+                attribute.id,
+            ));
+        }
+        let actual = self.current_value(storage, entity, attribute.id)?;
+        if actual != expected {
+            return Err(TransactionError::CompareAndSwapFailed {
+                entity,
+                attribute: attribute.id,
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies that asserting `value` for schema attribute `attribute_id` of an existing
+    /// attribute is a valid alteration. Attributes created by this transaction are not verified.
+    async fn verify_alteration<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        resolver: &AttributeResolver,
+        entity: u64,
+        attribute_id: u64,
+        value: &Value,
+    ) -> Result<(), S::Error> {
+        if !matches!(
+            attribute_id,
+            DB_ATTR_TYPE_ID | DB_ATTR_CARDINALITY_ID | DB_ATTR_UNIQUE_ID
+        ) {
+            return Ok(());
+        }
+        let altered = match resolver.resolve_id(storage, entity, self.tx_id).await {
+            Ok(altered) => altered,
+            Err(ResolveError::IdNotFound(_)) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let invalid = |reason| TransactionError::InvalidAlteration {
+            attribute: entity,
+            reason,
+        };
+        match (attribute_id, value) {
+            (DB_ATTR_TYPE_ID, &Value::U64(value_type))
+                if value_type != altered.definition.value_type as u64 =>
+            {
+                Err(invalid("value type can't be changed"))
+            }
+            (DB_ATTR_CARDINALITY_ID, &Value::U64(cardinality))
+                if cardinality == Cardinality::One as u64
+                    && altered.definition.cardinality == Cardinality::Many =>
+            {
+                // [?entity attribute _]
+                let restricts = Restricts::new(self.tx_id).with_attribute(entity);
+                let mut entities = HashSet::new();
+                for datom in storage.find(restricts) {
+                    if !entities.insert(datom?.entity) {
+                        return Err(invalid("existing entities have multiple values"));
+                    }
+                }
+                Ok(())
+            }
+            (DB_ATTR_UNIQUE_ID, _) if altered.definition.unique.is_none() => {
+                // [_ attribute ?value]
+                let restricts = Restricts::new(self.tx_id).with_attribute(entity);
+                let mut values = HashMap::new();
+                for datom in storage.find(restricts) {
+                    let datom = datom?;
+                    if let Some(other) = values.insert(datom.value, datom.entity) {
+                        if other != datom.entity {
+                            return Err(invalid("existing values are not unique"));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn verify_value_size<E>(&self, attribute: &Attribute, value: &Value) -> Result<(), E> {
+        let size = value.size_hint();
+        if size > self.config.max_value_size {
+            return Err(TransactionError::ValueTooLarge {
+                attribute: attribute.id,
+                size,
+                max_size: self.config.max_value_size,
+            });
         }
+        if !is_encodable(value) {
+            return Err(TransactionError::ValueNotEncodable(attribute.id));
+        }
+        Ok(())
     }
 
-    fn verify_uniqueness_tx<E>(&mut self, attribute: &Attribute, value: &Value) -> Result<(), E> {
-        // Find duplicate values within transaction.
-        if !self.unique_values.insert((attribute.id, value.clone())) {
-            return Err(TransactionError::DuplicateUniqueValue {
+    fn verify_predicates<E>(&self, attribute: &Attribute, value: &Value) -> Result<(), E> {
+        for name in &attribute.definition.preds {
+            let predicate = self
+                .config
+                .predicates
+                .get(name)
+                .ok_or_else(|| TransactionError::PredicateNotFound(name.clone()))?;
+            if !predicate.test(value) {
+                return Err(TransactionError::PredicateFailed {
+                    attribute: attribute.id,
+                    predicate: name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn verify_reference<'a, S: ReadStorage<'a>>(
+        &self,
+        storage: &'a S,
+        entity: u64,
+        attribute: &Attribute,
+        target: u64,
+    ) -> Result<(), S::Error> {
+        // Entities created by this transaction might not have any datoms yet.
+        let created = target == self.tx_id
+            || target == entity
+            || self.temp_ids.values().any(|&id| id == target)
+            || self.datoms.iter().any(|datom| datom.entity == target);
+        if created {
+            return Ok(());
+        }
+        // [target ?attribute ?value]
+        let restricts = Restricts::new(self.tx_id).with_entity(target);
+        if storage.find(restricts).next().transpose()?.is_none() {
+            return Err(TransactionError::MissingReference {
+                entity,
                 attribute: attribute.id,
-                value: value.clone(),
+                target,
             });
         }
         Ok(())
     }
 
+    fn verify_uniqueness_tx<E>(
+        &mut self,
+        entity: u64,
+        attribute: &Attribute,
+        value: &Value,
+    ) -> Result<(), E> {
+        // Find duplicate values of other entities within transaction.
+        let key = (attribute.id, value.clone());
+        if let Some(&other) = self.unique_values.get(&key) {
+            if other != entity {
+                return Err(TransactionError::DuplicateUniqueValue {
+                    attribute: attribute.id,
+                    value: value.clone(),
+                });
+            }
+        }
+        self.unique_values.insert(key, entity);
+        Ok(())
+    }
+
     fn verify_uniqueness_db<'a, S: ReadStorage<'a>>(
         &self,
+        entity: u64,
         attribute: &Attribute,
         value: &Value,
         storage: &'a S,
     ) -> Result<(), S::Error> {
-        // Find duplicate values previously saved.
+        // Find duplicate values of other entities previously saved.
         let restricts = Restricts::new(self.tx_id)
             .with_attribute(attribute.id)
             .with_value(value.clone());
-        if storage.find(restricts).count() > 0 {
-            return Err(TransactionError::DuplicateUniqueValue {
-                attribute: attribute.id,
-                value: value.clone(),
-            });
+        for datom in storage.find(restricts) {
+            if datom?.entity != entity {
+                return Err(TransactionError::DuplicateUniqueValue {
+                    attribute: attribute.id,
+                    value: value.clone(),
+                });
+            }
         }
         Ok(())
     }
 }
 
+/// Prefix of temp IDs generated for nested new entities, which are not reported to callers.
+const NESTED_TEMP_ID_PREFIX: &str = "rustomic.tx/nested-";
+
+/// Operations of a transaction after flattening nested entities. Each entity of `operations` is
+/// operated once, such that temp IDs are declared only once. `references` assert reverse
+/// attributes (e.g. `release/_artists`) as forward attributes of the referenced entities.
+#[derive(Default)]
+struct Flattened {
+    operations: Vec<EntityOperation>,
+    references: Vec<EntityOperation>,
+    nested: usize,
+}
+
+impl Flattened {
+    fn from<E>(operations: Vec<EntityOperation>) -> Result<Self, E> {
+        let mut flattened = Self::default();
+        for operation in operations {
+            flattened.push(operation, false)?;
+        }
+        Ok(flattened)
+    }
+
+    /// Pushes `operation` along with its nested entities, and returns a reference to the operated
+    /// entity if it's `referenced` or has reverse attributes.
+    fn push<E>(
+        &mut self,
+        mut operation: EntityOperation,
+        referenced: bool,
+    ) -> Result<Option<AttributeValue>, E> {
+        let has_reverse = operation
+            .attributes
+            .iter()
+            .any(|attribute| reverse_attribute(&attribute.attribute).is_some());
+        if matches!(operation.entity, OperatedEntity::New) && (referenced || has_reverse) {
+            self.nested += 1;
+            let temp_id = format!("{}{}", NESTED_TEMP_ID_PREFIX, self.nested);
+            operation.entity = OperatedEntity::TempId(temp_id);
+        }
+        let reference = match &operation.entity {
+            OperatedEntity::New => None,
+            OperatedEntity::Id(id) => Some(AttributeValue::Value(Value::Ref(*id))),
+            OperatedEntity::TempId(temp_id) => Some(AttributeValue::TempId(temp_id.clone())),
+            OperatedEntity::LookupRef(lookup_ref) => {
+                Some(AttributeValue::LookupRef(lookup_ref.clone()))
+            }
+        };
+
+        let attributes = std::mem::take(&mut operation.attributes);
+        for mut attribute in attributes {
+            if let AttributeValue::Entity(nested) = attribute.value {
+                attribute.value = self
+                    .push(*nested, true)?
+                    .expect("referenced entities are never new");
+            }
+            let Some(forward) = reverse_attribute(&attribute.attribute) else {
+                operation.attributes.push(attribute);
+                continue;
+            };
+            let referencing = match attribute.value {
+                AttributeValue::Value(Value::Ref(id)) => OperatedEntity::Id(id),
+                AttributeValue::TempId(temp_id) => OperatedEntity::TempId(temp_id),
+                AttributeValue::LookupRef(lookup_ref) => OperatedEntity::LookupRef(lookup_ref),
+                _ => {
+                    let AttributeIdentifier::Ident(ident) = attribute.attribute else {
+                        unreachable!("only idents can be reversed");
+                    };
+                    return Err(TransactionError::InvalidReverseReference(ident));
+                }
+            };
+            self.references.push(EntityOperation {
+                entity: referencing,
+                attributes: vec![AttributeOperation {
+                    attribute: forward,
+                    value: reference.clone().expect("reversed entities are never new"),
+                    op: attribute.op,
+                    expected: attribute.expected,
+                }],
+                partition: Partition::default(),
+            });
+        }
+        self.operations.push(operation);
+        Ok(reference)
+    }
+}
+
+/// Returns the forward attribute of a reverse attribute ident, e.g. `release/artists` for
+/// `release/_artists`.
+fn reverse_attribute(attribute: &AttributeIdentifier) -> Option<AttributeIdentifier> {
+    let AttributeIdentifier::Ident(ident) = attribute else {
+        return None;
+    };
+    let (namespace, name) = ident.split_once('/')?;
+    let name = name.strip_prefix('_')?;
+    Some(AttributeIdentifier::Ident(format!(
+        "{}/{}",
+        namespace, name
+    )))
+}
+
 fn generate_temp_ids<E>(
     operations: &[EntityOperation],
+    tx_id: u64,
     next_id: &mut NextId,
 ) -> Result<HashMap<String, u64>, E> {
-    let mut temp_ids = HashMap::with_capacity(operations.len());
+    let mut temp_ids = HashMap::with_capacity(operations.len() + 1);
+    temp_ids.insert(TX_TEMP_ID.to_string(), tx_id);
     for operation in operations {
         if let OperatedEntity::TempId(temp_id) = &operation.entity {
-            if temp_ids.insert(temp_id.clone(), next_id.get()).is_some() {
+            // The transaction entity may be operated any number of times.
+            if temp_id == TX_TEMP_ID {
+                continue;
+            }
+            if temp_ids
+                .insert(temp_id.clone(), next_id.get(operation.partition))
+                .is_some()
+            {
                 return Err(TransactionError::DuplicateTempId(temp_id.clone()));
             }
         };
@@ -190,7 +931,23 @@ fn generate_temp_ids<E>(
 }
 
 fn verify_type<E>(attribute: &Attribute, value: &Value) -> Result<(), E> {
-    if attribute.definition.value_type != ValueType::from(value) {
+    let elements_valid = match (&attribute.definition.tuple_type, value) {
+        (Some(TupleType::Composite(_)), _) => {
+            return Err(TransactionError::CompositeTupleTransacted(attribute.id));
+        }
+        (Some(TupleType::Homogeneous(value_type)), Value::Tuple(elements)) => elements
+            .iter()
+            .all(|element| is_element_of_type(element, *value_type)),
+        (Some(TupleType::Heterogeneous(value_types)), Value::Tuple(elements)) => {
+            elements.len() == value_types.len()
+                && elements
+                    .iter()
+                    .zip(value_types)
+                    .all(|(element, value_type)| is_element_of_type(element, *value_type))
+        }
+        _ => true,
+    };
+    if attribute.definition.value_type != ValueType::from(value) || !elements_valid {
         // Value type is incompatible with attribute, reject transaction.
         return Err(TransactionError::InvalidAttributeType {
             attribute_id: attribute.id,
@@ -201,11 +958,17 @@ fn verify_type<E>(attribute: &Attribute, value: &Value) -> Result<(), E> {
     Ok(())
 }
 
+/// Tuple elements may be nil regardless of their type.
+fn is_element_of_type(element: &Value, value_type: ValueType) -> bool {
+    *element == Value::Nil || ValueType::from(element) == value_type
+}
+
+/// Hands out entity IDs from a single sequence shared by all partitions.
 struct NextId(u64);
 
 impl NextId {
-    fn get(&mut self) -> u64 {
+    fn get(&mut self, partition: Partition) -> u64 {
         self.0 += 1;
-        self.0
+        partition.entity(self.0)
     }
 }
@@ -2,6 +2,8 @@ pub mod serde;
 
 use googletest::prelude::*;
 use rustomic::datom::*;
+use rustomic::schema::partition::Partition;
+use rustomic::schema::*;
 use rustomic::storage::restricts::*;
 use rustomic::storage::*;
 use std::collections::HashSet;
@@ -10,6 +12,7 @@ use std::result::Result;
 trait TestStorage {
     fn create() -> Self;
     fn save(&mut self, datoms: &[Datom]);
+    fn purge_history(&mut self, attributes: &[u64], before_tx: u64) -> usize;
     fn find(&self, restricts: Restricts) -> HashSet<Datom>;
     fn latest_entity_id(&self) -> u64;
 }
@@ -30,6 +33,12 @@ mod memory {
             self.0.save(datoms).expect("Unable to save datoms");
         }
 
+        fn purge_history(&mut self, attributes: &[u64], before_tx: u64) -> usize {
+            self.0
+                .purge_history(attributes, before_tx)
+                .expect("Unable to purge history")
+        }
+
         fn find(&self, restricts: Restricts) -> HashSet<Datom> {
             self.0.find(restricts).filter_map(Result::ok).collect()
         }
@@ -86,6 +95,16 @@ mod memory {
         ignore_retracted_values_impl::<InMemory>();
     }
 
+    #[test]
+    fn include_values_retracted_after_basis() {
+        include_values_retracted_after_basis_impl::<InMemory>();
+    }
+
+    #[test]
+    fn find_history_of_retracted_values() {
+        find_history_of_retracted_values_impl::<InMemory>();
+    }
+
     #[test]
     fn fetch_only_latest_value_for_attribute() {
         fetch_only_latest_value_for_attribute_impl::<InMemory>();
@@ -100,6 +119,83 @@ mod memory {
     fn fetch_latest_entity_id_with_datoms() {
         fetch_latest_entity_id_with_datoms_impl::<InMemory>();
     }
+
+    #[test]
+    fn fetch_latest_entity_id_across_partitions() {
+        fetch_latest_entity_id_across_partitions_impl::<InMemory>();
+    }
+
+    #[test]
+    fn find_datoms_by_value_of_unindexed_attribute() {
+        find_datoms_by_value_of_unindexed_attribute_impl::<InMemory>();
+    }
+
+    #[test]
+    fn find_datoms_by_value_of_indexed_attribute() {
+        find_datoms_by_value_of_indexed_attribute_impl::<InMemory>();
+    }
+
+    #[test]
+    fn backfill_index_when_attribute_becomes_indexed() {
+        backfill_index_when_attribute_becomes_indexed_impl::<InMemory>();
+    }
+
+    #[test]
+    fn find_datoms_referencing_entity() {
+        find_datoms_referencing_entity_impl::<InMemory>();
+    }
+
+    #[test]
+    fn find_datoms_referencing_entity_by_attribute() {
+        find_datoms_referencing_entity_by_attribute_impl::<InMemory>();
+    }
+
+    #[test]
+    fn drop_superseded_values_of_attributes_without_history() {
+        drop_superseded_values_of_attributes_without_history_impl::<InMemory>();
+    }
+
+    #[test]
+    fn purge_history_before_tx() {
+        purge_history_before_tx_impl::<InMemory>();
+    }
+
+    #[test]
+    fn excise_all_datoms_of_entity() {
+        excise_all_datoms_of_entity_impl::<InMemory>();
+    }
+
+    #[test]
+    fn excise_values_of_attributes() {
+        excise_values_of_attributes_impl::<InMemory>();
+    }
+
+    #[test]
+    fn read_datoms_from_snapshot() {
+        let mut storage = InMemoryStorage::new();
+        let datoms = [Datom::add(100, 101, 102, 103)];
+        storage.save(&datoms).expect("Unable to save datoms");
+
+        let snapshot = storage.snapshot();
+        storage
+            .save(&[
+                Datom::add(100, 101, 104, 105),
+                Datom::add(200, 101, 102, 105),
+            ])
+            .expect("Unable to save datoms");
+        let read_result: HashSet<Datom> = snapshot
+            .find(Restricts::new(u64::MAX).with_entity(100))
+            .map(|result| result.expect("Error while reading datom"))
+            .collect();
+
+        assert_that!(read_result, elements_are![eq_deref_of(&datoms[0])]);
+        assert_eq!(
+            100,
+            snapshot
+                .latest_entity_id()
+                .expect("Unable to fetch latest entity id")
+        );
+    }
 }
 
 mod disk {
@@ -126,6 +222,13 @@ mod disk {
                 .expect("Unable to save datoms");
         }
 
+        fn purge_history(&mut self, attributes: &[u64], before_tx: u64) -> usize {
+            DiskStorage::read_write(&self.path)
+                .expect("Unable to open DB")
+                .purge_history(attributes, before_tx)
+                .expect("Unable to purge history")
+        }
+
         fn find(&self, restricts: Restricts) -> HashSet<Datom> {
             DiskStorage::read_only(&self.path)
                 .expect("Unable to open DB")
@@ -187,6 +290,16 @@ mod disk {
         ignore_retracted_values_impl::<Disk>();
     }
 
+    #[test]
+    fn include_values_retracted_after_basis() {
+        include_values_retracted_after_basis_impl::<Disk>();
+    }
+
+    #[test]
+    fn find_history_of_retracted_values() {
+        find_history_of_retracted_values_impl::<Disk>();
+    }
+
     #[test]
     fn fetch_only_latest_value_for_attribute() {
         fetch_only_latest_value_for_attribute_impl::<Disk>();
@@ -201,6 +314,84 @@ mod disk {
     fn fetch_latest_entity_id_with_datoms() {
         fetch_latest_entity_id_with_datoms_impl::<Disk>();
     }
+
+    #[test]
+    fn fetch_latest_entity_id_across_partitions() {
+        fetch_latest_entity_id_across_partitions_impl::<Disk>();
+    }
+
+    #[test]
+    fn find_datoms_by_value_of_unindexed_attribute() {
+        find_datoms_by_value_of_unindexed_attribute_impl::<Disk>();
+    }
+
+    #[test]
+    fn find_datoms_by_value_of_indexed_attribute() {
+        find_datoms_by_value_of_indexed_attribute_impl::<Disk>();
+    }
+
+    #[test]
+    fn backfill_index_when_attribute_becomes_indexed() {
+        backfill_index_when_attribute_becomes_indexed_impl::<Disk>();
+    }
+
+    #[test]
+    fn find_datoms_referencing_entity() {
+        find_datoms_referencing_entity_impl::<Disk>();
+    }
+
+    #[test]
+    fn find_datoms_referencing_entity_by_attribute() {
+        find_datoms_referencing_entity_by_attribute_impl::<Disk>();
+    }
+
+    #[test]
+    fn drop_superseded_values_of_attributes_without_history() {
+        drop_superseded_values_of_attributes_without_history_impl::<Disk>();
+    }
+
+    #[test]
+    fn purge_history_before_tx() {
+        purge_history_before_tx_impl::<Disk>();
+    }
+
+    #[test]
+    fn excise_all_datoms_of_entity() {
+        excise_all_datoms_of_entity_impl::<Disk>();
+    }
+
+    #[test]
+    fn excise_values_of_attributes() {
+        excise_values_of_attributes_impl::<Disk>();
+    }
+
+    #[test]
+    fn read_datoms_from_snapshot() {
+        let path = TempDir::new("rustomic").expect("Unable to create temp dir");
+        let mut storage = DiskStorage::read_write(&path).expect("Unable to open DB");
+        let datoms = [Datom::add(100, 101, 102, 103)];
+        storage.save(&datoms).expect("Unable to save datoms");
+
+        let snapshot = storage.snapshot();
+        storage
+            .save(&[
+                Datom::add(100, 101, 104, 105),
+                Datom::add(200, 101, 102, 105),
+            ])
+            .expect("Unable to save datoms");
+        let read_result: HashSet<Datom> = snapshot
+            .find(Restricts::new(u64::MAX).with_entity(100))
+            .map(|result| result.expect("Error while reading datom"))
+            .collect();
+
+        assert_that!(read_result, elements_are![eq_deref_of(&datoms[0])]);
+        assert_eq!(
+            100,
+            snapshot
+                .latest_entity_id()
+                .expect("Unable to fetch latest entity id")
+        );
+    }
 }
 
 fn return_empty_result_if_no_datoms_match_search_criteria_impl<S: TestStorage>() {
@@ -375,6 +566,53 @@ fn ignore_retracted_values_impl<S: TestStorage>() {
     assert!(read_result.is_empty());
 }
 
+fn find_history_of_retracted_values_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let entity = 100;
+    let attribute = 101;
+    let datoms = [
+        // Add value 1 in tx 1000
+        Datom::add(entity, attribute, 1u64, 1000),
+        // Retract value 1 in tx 1001
+        Datom::retract(entity, attribute, 1u64, 1001),
+        // Add value 1 again in tx 1002
+        Datom::add(entity, attribute, 1u64, 1002),
+    ];
+    storage.save(&datoms);
+
+    let read_result = storage.find(
+        Restricts::new(1001)
+            .with_entity(entity)
+            .with_attribute(attribute)
+            .with_history(),
+    );
+
+    assert_eq!(HashSet::from_iter(datoms[..2].iter().cloned()), read_result);
+}
+
+fn include_values_retracted_after_basis_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let entity = 100;
+    let attribute = 101;
+    let datoms = [
+        // Add value 1 in tx 1000
+        Datom::add(entity, attribute, 1u64, 1000),
+        // Retract value 1 in tx 1002
+        Datom::retract(entity, attribute, 1u64, 1002),
+    ];
+    storage.save(&datoms);
+
+    let read_result = storage.find(
+        Restricts::new(1001)
+            .with_entity(entity)
+            .with_attribute(attribute),
+    );
+
+    assert_eq!(HashSet::from_iter(vec![datoms[0].clone()]), read_result);
+}
+
 fn fetch_only_latest_value_for_attribute_impl<S: TestStorage>() {
     let mut storage = S::create();
 
@@ -416,3 +654,261 @@ fn fetch_latest_entity_id_with_datoms_impl<S: TestStorage>() {
 
     assert_eq!(storage.latest_entity_id(), 102);
 }
+
+fn fetch_latest_entity_id_across_partitions_impl<S: TestStorage>() {
+    let mut storage = S::create();
+    let attribute = 100;
+    let tx = Partition::TX.entity(103);
+    let datoms = [
+        Datom::add(Partition::USER.entity(101), attribute, 1u64, tx),
+        Datom::add(Partition::custom(0).entity(102), attribute, 1u64, tx),
+        Datom::add(tx, attribute, 1u64, tx),
+    ];
+    storage.save(&datoms);
+
+    assert_eq!(storage.latest_entity_id(), 103);
+}
+
+fn find_datoms_by_value_of_unindexed_attribute_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let attribute = 101;
+    let datoms = [
+        Datom::add(100, attribute, 1u64, 1000),
+        Datom::add(102, attribute, 2u64, 1000),
+    ];
+    storage.save(&datoms);
+
+    let read_result = storage.find(
+        Restricts::new(1000)
+            .with_attribute(attribute)
+            .with_value(Value::U64(1)),
+    );
+
+    assert_that!(read_result, elements_are![eq_deref_of(&datoms[0])]);
+}
+
+fn find_datoms_by_value_of_indexed_attribute_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let attribute = 101;
+    storage.save(&[Datom::add(attribute, DB_ATTR_INDEX_ID, 1u64, 999)]);
+    let datoms = [
+        Datom::add(100, attribute, 1u64, 1000),
+        Datom::add(102, attribute, 2u64, 1000),
+    ];
+    storage.save(&datoms);
+
+    let read_result = storage.find(
+        Restricts::new(1000)
+            .with_attribute(attribute)
+            .with_value(Value::U64(1)),
+    );
+
+    assert_that!(read_result, elements_are![eq_deref_of(&datoms[0])]);
+}
+
+fn backfill_index_when_attribute_becomes_indexed_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let attribute = 101;
+    let datoms = [
+        Datom::add(100, attribute, 1u64, 1000),
+        Datom::add(102, attribute, 2u64, 1000),
+    ];
+    storage.save(&datoms);
+    storage.save(&[Datom::add(attribute, DB_ATTR_INDEX_ID, 1u64, 1001)]);
+
+    let read_result = storage.find(
+        Restricts::new(1001)
+            .with_attribute(attribute)
+            .with_value(Value::U64(2)),
+    );
+
+    assert_that!(read_result, elements_are![eq_deref_of(&datoms[1])]);
+}
+
+fn find_datoms_referencing_entity_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let artist = 42;
+    let release_artists = 101;
+    let track_artists = 102;
+    let datoms = [
+        Datom::add(100, release_artists, Value::Ref(artist), 1000),
+        Datom::add(103, track_artists, Value::Ref(artist), 1000),
+        Datom::add(104, release_artists, Value::Ref(43), 1000),
+        Datom::add(105, release_artists, Value::U64(artist), 1000),
+        Datom::add(106, release_artists, Value::Ref(artist), 1000),
+        Datom::retract(106, release_artists, Value::Ref(artist), 1001),
+    ];
+    storage.save(&datoms);
+
+    let read_result = storage.find(Restricts::new(1001).with_value(Value::Ref(artist)));
+
+    assert_that!(
+        read_result,
+        unordered_elements_are![eq_deref_of(&datoms[0]), eq_deref_of(&datoms[1])]
+    );
+}
+
+fn find_datoms_referencing_entity_by_attribute_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let artist = 42;
+    let release_artists = 101;
+    let track_artists = 102;
+    let datoms = [
+        Datom::add(100, release_artists, Value::Ref(artist), 1000),
+        Datom::add(103, track_artists, Value::Ref(artist), 1000),
+        Datom::add(104, release_artists, Value::Ref(artist), 1000),
+    ];
+    storage.save(&datoms);
+
+    let read_result = storage.find(
+        Restricts::new(1000)
+            .with_attribute(release_artists)
+            .with_value(Value::Ref(artist)),
+    );
+
+    assert_that!(
+        read_result,
+        unordered_elements_are![eq_deref_of(&datoms[0]), eq_deref_of(&datoms[2])]
+    );
+}
+
+fn drop_superseded_values_of_attributes_without_history_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let entity = 100;
+    let attribute = 101;
+    storage.save(&[Datom::add(attribute, DB_ATTR_NO_HISTORY_ID, 1u64, 999)]);
+    storage.save(&[Datom::add(entity, attribute, 1u64, 1000)]);
+    let current = Datom::add(entity, attribute, 2u64, 1001);
+    storage.save(&[
+        current.clone(),
+        Datom::retract(entity, attribute, 1u64, 1001),
+    ]);
+
+    let restricts = Restricts::new(1000)
+        .with_entity(entity)
+        .with_attribute(attribute);
+    assert!(storage.find(restricts).is_empty());
+
+    let restricts = Restricts::new(1001)
+        .with_entity(entity)
+        .with_attribute(attribute);
+    assert_that!(
+        storage.find(restricts),
+        elements_are![eq_deref_of(&current)]
+    );
+}
+
+fn purge_history_before_tx_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let entity = 100;
+    let attribute = 101;
+    let other_attribute = 102;
+    storage.save(&[
+        Datom::add(entity, attribute, 1u64, 1000),
+        Datom::add(entity, other_attribute, 1u64, 1000),
+    ]);
+    storage.save(&[
+        Datom::add(entity, attribute, 2u64, 1001),
+        Datom::retract(entity, attribute, 1u64, 1001),
+        Datom::retract(entity, other_attribute, 1u64, 1001),
+    ]);
+    storage.save(&[
+        Datom::add(entity, attribute, 3u64, 1002),
+        Datom::retract(entity, attribute, 2u64, 1002),
+    ]);
+
+    let purged = storage.purge_history(&[attribute], 1002);
+
+    assert_eq!(2, purged);
+    // Values retracted before 1002 are gone, the history of other attributes is kept
+    let read_result = storage.find(Restricts::new(1000).with_entity(entity));
+    assert_that!(
+        read_result,
+        elements_are![eq_deref_of(&Datom::add(
+            entity,
+            other_attribute,
+            1u64,
+            1000
+        ))]
+    );
+    // Values retracted at 1002 are kept
+    let read_result = storage.find(Restricts::new(1001).with_entity(entity));
+    assert_that!(
+        read_result,
+        elements_are![eq_deref_of(&Datom::add(entity, attribute, 2u64, 1001))]
+    );
+}
+
+fn excise_all_datoms_of_entity_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let entity = 100;
+    let other_entity = 102;
+    let name = 101;
+    let friend = 103;
+    storage.save(&[Datom::add(name, DB_ATTR_INDEX_ID, 1u64, 999)]);
+    storage.save(&[
+        Datom::add(entity, name, "John", 1000),
+        Datom::add(entity, friend, Value::Ref(other_entity), 1000),
+        Datom::add(other_entity, name, "Paul", 1000),
+    ]);
+    storage.save(&[
+        Datom::add(entity, name, "John Lennon", 1001),
+        Datom::retract(entity, name, "John", 1001),
+    ]);
+    let excision = [Datom::add(200, DB_EXCISE_ID, Value::Ref(entity), 1002)];
+    storage.save(&excision);
+
+    // Nothing is left of the entity at any point in time, in any index
+    for tx in [1000, 1001, 1002] {
+        assert!(storage
+            .find(Restricts::new(tx).with_entity(entity))
+            .is_empty());
+        assert!(storage
+            .find(
+                Restricts::new(tx)
+                    .with_attribute(name)
+                    .with_value(Value::str("John"))
+            )
+            .is_empty());
+        assert!(storage
+            .find(Restricts::new(tx).with_value(Value::Ref(other_entity)))
+            .is_empty());
+    }
+    let read_result = storage.find(Restricts::new(1002).with_attribute(name));
+    assert_that!(
+        read_result,
+        elements_are![eq_deref_of(&Datom::add(other_entity, name, "Paul", 1000))]
+    );
+    let read_result = storage.find(Restricts::new(1002).with_entity(200));
+    assert_that!(read_result, elements_are![eq_deref_of(&excision[0])]);
+}
+
+fn excise_values_of_attributes_impl<S: TestStorage>() {
+    let mut storage = S::create();
+
+    let entity = 100;
+    let name = 101;
+    let email = 102;
+    storage.save(&[
+        Datom::add(entity, name, "John", 1000),
+        Datom::add(entity, email, "john@beatles.com", 1000),
+    ]);
+    storage.save(&[
+        Datom::add(200, DB_EXCISE_ID, Value::Ref(entity), 1001),
+        Datom::add(200, DB_EXCISE_ATTRS_ID, Value::Ref(email), 1001),
+    ]);
+
+    let read_result = storage.find(Restricts::new(1001).with_entity(entity));
+    assert_that!(
+        read_result,
+        elements_are![eq_deref_of(&Datom::add(entity, name, "John", 1000))]
+    );
+}
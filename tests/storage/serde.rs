@@ -1,10 +1,12 @@
 extern crate rustomic;
 
+use ordered_float::OrderedFloat;
 use quickcheck::*;
 use quickcheck_macros::quickcheck;
 use rust_decimal::prelude::*;
 use rustomic::datom::*;
 use rustomic::storage::*;
+use uuid::Uuid;
 
 #[quickcheck]
 fn test_eavt_serialization(datom: ArbitraryDatom) {
@@ -36,6 +38,28 @@ fn test_avet_serialization(datom: ArbitraryDatom) {
     assert_eq!(datom, deserialized.unwrap());
 }
 
+#[quickcheck]
+fn test_vaet_serialization(datom: ArbitraryDatom) {
+    let ArbitraryDatom(datom) = datom;
+    let serialized = serde::datom::serialize::vaet(&datom);
+    let deserialized = serde::datom::deserialize(serde::Index::Vaet, &serialized);
+
+    assert!(deserialized.is_ok());
+    assert_eq!(datom, deserialized.unwrap());
+}
+
+#[quickcheck]
+fn test_value_serialization_preserves_order(values: SameTypeValues) {
+    let SameTypeValues(value1, value2) = values;
+    let serialize =
+        |value: &Value| serde::datom::serialize::avet(&Datom::add(1, 2, value.clone(), 3));
+
+    assert_eq!(
+        value1.cmp(&value2),
+        serialize(&value1).cmp(&serialize(&value2))
+    );
+}
+
 #[derive(Debug, Clone)]
 struct ArbitraryDatom(Datom);
 
@@ -73,15 +97,24 @@ struct ArbitraryValue(Value);
 
 impl Arbitrary for ArbitraryValue {
     fn arbitrary(g: &mut Gen) -> Self {
-        Self(match g.choose(&[0, 1, 2, 3, 4, 5]) {
-            Some(0) => Value::Nil,
-            Some(1) => Value::I64(i64::arbitrary(g)),
-            Some(2) => Value::U64(u64::arbitrary(g)),
-            Some(3) => Value::Decimal(arbitrary_decimal(g)),
-            Some(4) => Value::Str(String::arbitrary(g)),
-            Some(5) => Value::Ref(u64::arbitrary(g)),
-            _ => unreachable!(),
-        })
+        Self(
+            match g.choose(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]) {
+                Some(0) => Value::Nil,
+                Some(1) => Value::I64(i64::arbitrary(g)),
+                Some(2) => Value::U64(u64::arbitrary(g)),
+                Some(3) => Value::Decimal(arbitrary_decimal(g)),
+                Some(4) => Value::Str(String::arbitrary(g)),
+                Some(5) => Value::Ref(u64::arbitrary(g)),
+                Some(6) => Value::Bool(bool::arbitrary(g)),
+                Some(7) => Value::Keyword(String::arbitrary(g)),
+                Some(8) => Value::Instant(i64::arbitrary(g)),
+                Some(9) => Value::Uuid(Uuid::from_u128(u128::arbitrary(g))),
+                Some(10) => Value::Double(OrderedFloat(f64::arbitrary(g))),
+                Some(11) => Value::Bytes(Vec::arbitrary(g)),
+                Some(12) => Value::BigInt(arbitrary_big_int(g)),
+                _ => unreachable!(),
+            },
+        )
     }
 
     fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
@@ -91,11 +124,25 @@ impl Arbitrary for ArbitraryValue {
 
         let chain = single_shrinker(Self(Value::Nil)).chain(
             match &self.0 {
-                Value::Nil | Value::Decimal(_) => empty_shrinker(),
+                Value::Nil
+                | Value::Decimal(_)
+                | Value::Uuid(_)
+                | Value::BigInt(_)
+                | Value::Tuple(_) => empty_shrinker(),
                 Value::I64(value) => Box::new(value.shrink().map(Value::I64)),
                 Value::U64(value) => Box::new(value.shrink().map(Value::U64)),
                 Value::Str(value) => Box::new(value.to_string().shrink().map(Value::Str)),
                 Value::Ref(value) => Box::new(value.shrink().map(Value::Ref)),
+                Value::Bool(value) => Box::new(value.shrink().map(Value::Bool)),
+                Value::Keyword(value) => Box::new(value.to_string().shrink().map(Value::Keyword)),
+                Value::Instant(value) => Box::new(value.shrink().map(Value::Instant)),
+                Value::Bytes(value) => Box::new(value.shrink().map(Value::Bytes)),
+                Value::Double(value) => Box::new(
+                    value
+                        .0
+                        .shrink()
+                        .map(|value| Value::Double(OrderedFloat(value))),
+                ),
             }
             .map(Self),
         );
@@ -104,6 +151,36 @@ impl Arbitrary for ArbitraryValue {
     }
 }
 
+/// Two values of a type whose encoding is order preserving.
+#[derive(Debug, Clone)]
+struct SameTypeValues(Value, Value);
+
+impl Arbitrary for SameTypeValues {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let value_type = *g.choose(&[0, 1, 2, 3, 4, 5, 6]).unwrap();
+        let mut value = || match value_type {
+            0 => Value::Bool(bool::arbitrary(g)),
+            1 => Value::Instant(i64::arbitrary(g)),
+            2 => Value::Uuid(Uuid::from_u128(u128::arbitrary(g))),
+            3 => Value::Double(OrderedFloat(f64::arbitrary(g))),
+            4 => Value::Bytes(Vec::arbitrary(g)),
+            5 => Value::BigInt(arbitrary_big_int(g)),
+            _ => Value::Tuple(arbitrary_tuple(g, |g| Value::Instant(i64::arbitrary(g)))),
+        };
+        Self(value(), value())
+    }
+}
+
+/// Tuples are kept short, as elements might be tuples themselves.
+fn arbitrary_tuple(g: &mut Gen, element: impl Fn(&mut Gen) -> Value) -> Vec<Value> {
+    let length = usize::arbitrary(g) % 4;
+    (0..length).map(|_| element(g)).collect()
+}
+
+fn arbitrary_big_int(g: &mut Gen) -> BigInt {
+    BigInt::from_signed_bytes_be(&Vec::arbitrary(g))
+}
+
 fn arbitrary_decimal(g: &mut Gen) -> Decimal {
     let mut arr = [0u8; 16];
     for x in &mut arr {